@@ -1,17 +1,23 @@
-use clap::{Arg, ArgMatches, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use colored::*;
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use dirs_next::home_dir;
-use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command as processCommand, Stdio};
+use std::process::{self, Child, Command as processCommand, ExitStatus, Stdio};
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
@@ -20,88 +26,704 @@ use tui::{backend::CrosstermBackend, Terminal};
 
 const CONFIG_FILE_PATH: &str = "~/.config/bsh/commands.json";
 
+// Shell widgets for `bsh init`, bound to Ctrl-G they insert the command picked in the TUI
+// into the current command line
+const BASH_WIDGET: &str = r#"__bsh_widget() {
+  local selected
+  selected="$(bsh --print)" || return
+  READLINE_LINE="${READLINE_LINE:0:$READLINE_POINT}${selected}${READLINE_LINE:$READLINE_POINT}"
+  READLINE_POINT=$(( READLINE_POINT + ${#selected} ))
+}
+bind -x '"\C-g": __bsh_widget'
+"#;
+
+const ZSH_WIDGET: &str = r#"__bsh_widget() {
+  local selected
+  selected="$(bsh --print </dev/tty)"
+  if [[ $? -eq 0 && -n $selected ]]; then
+    LBUFFER="${LBUFFER}${selected}"
+  fi
+  zle reset-prompt
+}
+zle -N __bsh_widget
+bindkey '^G' __bsh_widget
+"#;
+
+const FISH_WIDGET: &str = r#"function __bsh_widget
+    set -l selected (bsh --print)
+    and test -n "$selected"
+    and commandline -i -- "$selected"
+    commandline -f repaint
+end
+bind \cg __bsh_widget
+"#;
+
+// Shown in the TUI before a category is given a reserved name, a second Enter confirms it
+const RESERVED_WARNING: &str = "Reserved, Enter to keep";
+
+// Completion scripts for `bsh completions`, the candidates come from `bsh __complete` so
+// categories and aliases are completed from the config
+const BASH_COMPLETION: &str = r#"_bsh() {
+  local IFS=$'\n'
+  COMPREPLY=($(bsh __complete -- "${COMP_WORDS[@]:1:COMP_CWORD}"))
+}
+complete -o default -F _bsh bsh
+"#;
+
+const ZSH_COMPLETION: &str = r#"#compdef bsh
+
+_bsh() {
+  local -a candidates
+  candidates=(${(f)"$(bsh __complete -- "${(@)words[2,CURRENT]}")"})
+  compadd -a candidates
+}
+
+compdef _bsh bsh
+"#;
+
+const FISH_COMPLETION: &str = r#"function __bsh_complete
+    set -l tokens (commandline -opc)
+    set -e tokens[1]
+    set -l current (commandline -ct)
+    bsh __complete -- $tokens "$current"
+end
+
+complete -c bsh -f -a '(__bsh_complete)'
+"#;
+
+// Exit codes used by `run` when bsh itself fails before or instead of the stored command
+const EXIT_FAILURE: i32 = 1;
+const EXIT_CATEGORY_NOT_FOUND: i32 = 3;
+const EXIT_ALIAS_NOT_FOUND: i32 = 4;
+const EXIT_PLACEHOLDER_ABORTED: i32 = 5;
+const EXIT_SPAWN_FAILED: i32 = 127;
+
+// Pid of the running stored command, used to forward signals sent to bsh
+#[cfg(unix)]
+static CHILD_PID: AtomicI32 = AtomicI32::new(0);
+
 #[derive(Serialize, Deserialize, Clone)]
 struct Config {
-    categories: HashMap<String, HashMap<String, String>>,
+    #[serde(deserialize_with = "deserialize_categories")]
+    categories: IndexMap<String, IndexMap<String, CommandEntry>>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    category_settings: HashMap<String, CategorySettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+    #[serde(default, skip_serializing_if = "SortMode::is_manual")]
+    sort: SortMode,
+}
+
+#[derive(Clone, Copy)]
+enum OnConflict {
+    Fail,
+    Overwrite,
+    Suffix,
+}
+
+// The order categories and commands are listed in, manual is the order they are stored in
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+enum SortMode {
+    #[default]
+    Manual,
+    Alphabetical,
+    MostUsed,
+    RecentlyUsed,
+}
+
+impl SortMode {
+    const ALL: [SortMode; 4] = [
+        SortMode::Manual,
+        SortMode::Alphabetical,
+        SortMode::MostUsed,
+        SortMode::RecentlyUsed,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            SortMode::Manual => "manual",
+            SortMode::Alphabetical => "alphabetical",
+            SortMode::MostUsed => "most-used",
+            SortMode::RecentlyUsed => "recently-used",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<SortMode> {
+        SortMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    fn next(self) -> SortMode {
+        let index = SortMode::ALL.iter().position(|mode| *mode == self).unwrap();
+        SortMode::ALL[(index + 1) % SortMode::ALL.len()]
+    }
+
+    fn is_manual(&self) -> bool {
+        *self == SortMode::Manual
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct CommandEntry {
+    command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    env: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    // Unix timestamps in seconds, unknown for commands added before they were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated: Option<u64>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    uses: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_used: Option<u64>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl CommandEntry {
+    fn new(command: &str) -> Self {
+        CommandEntry {
+            command: command.to_string(),
+            author: env::var("USER").or_else(|_| env::var("USERNAME")).ok(),
+            created: Some(now()),
+            ..Default::default()
+        }
+    }
+}
+
+// Settings shared by all commands of a category
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct CategorySettings {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    env: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+}
+
+// The program a command is passed to, `sh -c` unless another shell is configured
+struct Interpreter {
+    argv: Vec<String>,
+    // sh compatible shells take `$0` before the arguments of the command
+    posix: bool,
+    // Shells get quoted placeholder values and arguments appended to the command
+    shell: bool,
+}
+
+// Older configs store each command as just its command string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredCommand {
+    Command(String),
+    Entry(Box<CommandEntry>),
+}
+
+impl From<StoredCommand> for CommandEntry {
+    fn from(stored: StoredCommand) -> Self {
+        match stored {
+            StoredCommand::Command(command) => CommandEntry {
+                command,
+                ..Default::default()
+            },
+            StoredCommand::Entry(entry) => *entry,
+        }
+    }
+}
+
+fn deserialize_categories<'de, D>(
+    deserializer: D,
+) -> Result<IndexMap<String, IndexMap<String, CommandEntry>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let categories =
+        IndexMap::<String, IndexMap<String, StoredCommand>>::deserialize(deserializer)?;
+    Ok(categories
+        .into_iter()
+        .map(|(category, commands)| {
+            let commands = commands
+                .into_iter()
+                .map(|(alias, stored)| (alias, stored.into()))
+                .collect();
+            (category, commands)
+        })
+        .collect())
 }
 
 #[derive(Default)]
 struct AppState {
     categories: Vec<String>,
-    commands: HashMap<String, Vec<(String, String)>>,
+    commands: HashMap<String, Vec<(String, CommandEntry)>>,
     selected_category: Option<usize>,
     selected_command: Option<usize>,
     selected_button: Option<usize>,
     mode: Mode,
     input_mode: InputMode,
     input: String,
+    pending_command: CommandEntry,
+    pending_cwd: Option<PathBuf>,
+    placeholders: Vec<Placeholder>,
+    placeholder_values: Vec<String>,
+    selected_choice: usize,
+    dry_run: bool,
+    input_error: Option<String>,
+    move_targets: Vec<String>,
+    // A key handled as if it was pressed next, used to run a hit of the filter
+    queued_key: Option<KeyEvent>,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Default)]
 enum Mode {
+    #[default]
     Category,
     Command,
     Buttons,
 }
 
-impl Default for Mode {
-    fn default() -> Self {
-        Mode::Category
-    }
-}
-
-#[derive(PartialEq)]
+#[derive(PartialEq, Default)]
 enum InputMode {
+    #[default]
     Normal,
     Editing,
     Adding,
+    Placeholder,
+    WorkingDirectory,
+    Renaming,
+    Moving,
+    Filtering,
 }
 
-impl Default for InputMode {
-    fn default() -> Self {
-        InputMode::Normal
-    }
+// Values and flags given to `run` that control how placeholders are resolved
+#[derive(Default)]
+struct RunOptions {
+    vars: HashMap<String, String>,
+    values: Vec<String>,
+    args: Vec<String>,
+    no_input: bool,
+    show_env: bool,
+    dry_run: bool,
+}
+
+// A `<[name]>`, `<[name:default]>`, `<[name|choice,...]>` or `<[name@generator]>`
+// placeholder, with every place it occurs in the command. Secret placeholders
+// (`<[*name]>`) are read without echo
+#[derive(Clone)]
+struct Placeholder {
+    name: String,
+    default: Option<String>,
+    choices: Vec<String>,
+    generator: Option<String>,
+    secret: bool,
+    occurrences: Vec<Occurrence>,
+}
+
+// The `start..end` span of a placeholder in the command, `raw` occurrences (`<[!name]>`)
+// are substituted without shell quoting
+#[derive(Clone)]
+struct Occurrence {
+    start: usize,
+    end: usize,
+    raw: bool,
+    quotes: Quotes,
+}
+
+// The quotes a placeholder stands in, its value is quoted to stay literal inside them
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Quotes {
+    None,
+    Single,
+    Double,
 }
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<String> = env::args().collect();
 
     if args.len() == 1 {
-        start_tui().unwrap();
-        return;
+        let exit_code = start_tui(false).unwrap();
+        process::exit(exit_code);
     }
 
     let mut clap_args = args.clone();
 
-    // Check if the first argument is not a known subcommand and not a flag
-    if args.len() > 1
-        && ![
-            "run",
-            "r",
-            "add",
-            "a",
-            "delete",
-            "d",
-            "help",
-            "-V",
-            "--version",
-            "-h",
-            "--help",
-            "list",
-            "l",
-            "update",
-            "u",
-        ]
-        .contains(&args[1].as_str())
-    {
+    // `bsh -- <CATEGORY> <ALIAS>` runs categories that are named like a subcommand
+    if args.len() > 1 && args[1] == "--" {
+        clap_args[1] = "run".to_string();
+    } else if args.len() > 1 && !is_reserved(&args[1]) {
         // Prepend the 'run' command if it appears to be missing
         clap_args.insert(1, "run".to_string());
     }
-    let matches = Command::new("bsh")
+    let matches = build_cli().get_matches_from(clap_args);
+
+    if matches.get_flag("print") {
+        let exit_code = start_tui(true).unwrap();
+        process::exit(exit_code);
+    }
+
+    let pathbuf = check_for_config_file_or_create();
+    let path = pathbuf.as_path();
+
+    let data = fs::read_to_string(path).expect("Unable to read file");
+    let mut config: Config = serde_json::from_str(&data).expect("Unable to parse JSON");
+
+    if let Some(shell) = matches.get_one::<String>("default-shell") {
+        config.shell = Some(shell.clone()).filter(|shell| !shell.is_empty());
+        update_config_file(&config, path);
+        match &config.shell {
+            Some(shell) => println!("Commands are run with '{}' by default", shell),
+            None => println!("Commands are run with 'sh' by default"),
+        }
+        return;
+    }
+
+    match matches.subcommand() {
+        Some(("add", sub_m)) => {
+            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
+            let alias = sub_m.get_one::<String>("ALIAS");
+            let command = sub_m.get_one::<String>("COMMAND");
+
+            if alias.is_some() == command.is_some() && !check_if_category_exists(category, &config)
+            {
+                warn_if_reserved(category);
+            }
+            match (alias, command) {
+                (Some(alias), Some(command)) => {
+                    let mut entry = CommandEntry::new(command);
+                    entry.cwd = sub_m.get_one::<String>("cwd").cloned();
+                    entry.shell = sub_m.get_one::<String>("shell").cloned();
+                    update_env(&mut entry.env, sub_m);
+                    update_details(&mut entry, sub_m);
+                    add_command(category, &entry, alias, &mut config, path);
+                }
+                (None, None) => {
+                    add_category_to_config(category, &mut config, path);
+                    let settings = config
+                        .category_settings
+                        .entry(category.clone())
+                        .or_default();
+                    update_env(&mut settings.env, sub_m);
+                    if let Some(shell) = sub_m.get_one::<String>("shell") {
+                        settings.shell = Some(shell.clone()).filter(|shell| !shell.is_empty());
+                    }
+                    if settings.env.is_empty() && settings.shell.is_none() {
+                        config.category_settings.remove(category);
+                    }
+                    update_config_file(&config, path);
+                }
+                _ => {
+                    eprintln!("Error: When specifying an alias, a command must also be provided, and vice versa.");
+                }
+            }
+        }
+        Some(("run", sub_m)) => {
+            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
+            let alias = sub_m.get_one::<String>("ALIAS").unwrap();
+            let options = RunOptions {
+                vars: sub_m
+                    .get_many::<(String, String)>("var")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect(),
+                values: sub_m
+                    .get_many::<String>("VALUES")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect(),
+                args: sub_m
+                    .get_many::<String>("ARGS")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect(),
+                no_input: sub_m.get_flag("no-input"),
+                show_env: sub_m.get_flag("show-env"),
+                dry_run: sub_m.get_flag("dry-run"),
+            };
+            let (category, alias) = match resolve_names(category, alias, &config, &options) {
+                Ok(names) => names,
+                Err(exit_code) => process::exit(exit_code),
+            };
+            process::exit(run_command(&category, &alias, &mut config, path, &options));
+        }
+        Some(("delete", sub_m)) => {
+            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
+            let alias = sub_m.get_one::<String>("ALIAS");
+
+            match alias {
+                Some(alias) => {
+                    remove_command_from_config(category, alias, &mut config, path);
+                }
+                None => {
+                    remove_category_from_config(category, &mut config, path);
+                }
+            }
+        }
+        Some(("rename", sub_m)) => {
+            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
+            let alias = sub_m.get_one::<String>("ALIAS").unwrap();
+
+            // With only two names the category itself is renamed
+            let new_alias = sub_m.get_one::<String>("NEW_NAME");
+            let result = match new_alias {
+                Some(new_alias) => rename_command(category, alias, new_alias, &mut config),
+                None => rename_category(category, alias, &mut config),
+            };
+            match result {
+                Ok(()) => {
+                    update_config_file(&config, path);
+                    if new_alias.is_none() {
+                        warn_if_reserved(alias);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(EXIT_FAILURE);
+                }
+            }
+        }
+        Some((name @ ("mv" | "cp"), sub_m)) => {
+            let source = sub_m.get_one::<String>("SOURCE").unwrap();
+            let destination = sub_m.get_one::<String>("DESTINATION").unwrap();
+            let on_conflict = match sub_m.get_one::<String>("on-conflict").unwrap().as_str() {
+                "overwrite" => OnConflict::Overwrite,
+                "suffix" => OnConflict::Suffix,
+                _ => OnConflict::Fail,
+            };
+
+            let Some((category, alias)) = source.split_once('/') else {
+                eprintln!("Expected <CATEGORY>/<ALIAS>, got '{}'", source);
+                process::exit(EXIT_FAILURE);
+            };
+            let (target_category, target_alias) =
+                destination.split_once('/').unwrap_or((destination, alias));
+
+            let new_category = !check_if_category_exists(target_category, &config);
+            let copy = name == "cp";
+            match transfer_command(
+                category,
+                alias,
+                target_category,
+                target_alias,
+                on_conflict,
+                copy,
+                &mut config,
+            ) {
+                Ok(target_alias) => {
+                    update_config_file(&config, path);
+                    if new_category {
+                        println!(
+                            "Adding Category '{}', because it does not exist",
+                            target_category
+                        );
+                        warn_if_reserved(target_category);
+                    }
+                    println!(
+                        "{} '{}' to '{}/{}'",
+                        if copy { "Copied" } else { "Moved" },
+                        source,
+                        target_category,
+                        target_alias
+                    );
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(EXIT_FAILURE);
+                }
+            }
+        }
+        Some(("update", sub_m)) => {
+            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
+            let alias = sub_m.get_one::<String>("ALIAS").unwrap();
+            let command = sub_m.get_one::<String>("COMMAND");
+
+            let stored = config
+                .categories
+                .get(category)
+                .and_then(|commands| commands.get(alias))
+                .cloned();
+            let mut entry = match (stored, command) {
+                (Some(entry), _) => entry,
+                (None, Some(command)) => CommandEntry::new(command),
+                (None, None) => {
+                    eprintln!(
+                        "Command '{}' does not exist in category '{}', give a command to add it",
+                        alias, category
+                    );
+                    process::exit(EXIT_ALIAS_NOT_FOUND);
+                }
+            };
+            if let Some(command) = command {
+                entry.command = command.clone();
+            }
+            if !check_if_category_exists(category, &config) {
+                warn_if_reserved(category);
+            }
+            if let Some(cwd) = sub_m.get_one::<String>("cwd") {
+                entry.cwd = Some(cwd.clone()).filter(|cwd| !cwd.is_empty());
+            }
+            if let Some(shell) = sub_m.get_one::<String>("shell") {
+                entry.shell = Some(shell.clone()).filter(|shell| !shell.is_empty());
+            }
+            update_env(&mut entry.env, sub_m);
+            update_details(&mut entry, sub_m);
+
+            update_command(category, &entry, alias, &mut config, path);
+        }
+        Some(("list", sub_m)) => {
+            handle_list_command(sub_m, &config);
+        }
+        Some(("search", sub_m)) => {
+            let query: Vec<&str> = sub_m
+                .get_many::<String>("QUERY")
+                .unwrap()
+                .map(String::as_str)
+                .collect();
+            let results = search_commands(&query.join(" "), &config);
+            if results.is_empty() {
+                println!("{}", "No matching commands found.".yellow().bold());
+                process::exit(EXIT_FAILURE);
+            }
+
+            match sub_m.get_one::<usize>("run") {
+                Some(&n) => {
+                    let Some(result) = n.checked_sub(1).and_then(|i| results.get(i)) else {
+                        eprintln!("There is no result {}, found {}", n, results.len());
+                        process::exit(EXIT_FAILURE);
+                    };
+                    let (category, alias) = (result.category.clone(), result.alias.clone());
+                    process::exit(run_command(
+                        &category,
+                        &alias,
+                        &mut config,
+                        path,
+                        &RunOptions::default(),
+                    ));
+                }
+                None => print_search_results(&results, &config),
+            }
+        }
+        Some(("init", sub_m)) => match sub_m.get_one::<String>("SHELL").unwrap().as_str() {
+            "bash" => print!("{}", BASH_WIDGET),
+            "zsh" => print!("{}", ZSH_WIDGET),
+            _ => print!("{}", FISH_WIDGET),
+        },
+        Some(("completions", sub_m)) => match sub_m.get_one::<String>("SHELL").unwrap().as_str() {
+            "bash" => print!("{}", BASH_COMPLETION),
+            "zsh" => print!("{}", ZSH_COMPLETION),
+            _ => print!("{}", FISH_COMPLETION),
+        },
+        Some(("__complete", sub_m)) => {
+            let words: Vec<String> = sub_m
+                .get_many::<String>("WORDS")
+                .unwrap_or_default()
+                .cloned()
+                .collect();
+            for candidate in complete(&words, &config) {
+                println!("{}", candidate);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Applies the --env and --unset-env arguments of add and update
+fn update_env(env: &mut HashMap<String, String>, matches: &ArgMatches) {
+    for (name, value) in matches
+        .get_many::<(String, String)>("env")
+        .unwrap_or_default()
+    {
+        env.insert(name.clone(), value.clone());
+    }
+    for name in matches.get_many::<String>("unset-env").unwrap_or_default() {
+        env.remove(name);
+    }
+}
+
+// Applies the --desc and --tag arguments of add and update, given tags replace the existing ones
+fn update_details(entry: &mut CommandEntry, matches: &ArgMatches) {
+    if let Some(description) = matches.get_one::<String>("desc") {
+        entry.description = Some(description.clone()).filter(|desc| !desc.is_empty());
+    }
+    if let Some(tags) = matches.get_many::<String>("tag") {
+        entry.tags = tags.filter(|tag| !tag.is_empty()).cloned().collect();
+    }
+}
+
+fn parse_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{}'", var)),
+    }
+}
+
+fn on_conflict_arg() -> Arg {
+    Arg::new("on-conflict")
+        .help("What to do when the alias is already taken in the destination: fail, overwrite the existing command, or add a numeric suffix to the alias")
+        .long("on-conflict")
+        .value_name("ACTION")
+        .value_parser(["fail", "overwrite", "suffix"])
+        .default_value("fail")
+}
+
+// Subcommands, aliases and top-level flags that clap picks up as the first argument, a
+// category with one of these names can only be run with `bsh run` or `bsh --`
+fn reserved_names() -> Vec<String> {
+    let mut cli = build_cli();
+    cli.build();
+
+    let subcommands = cli.get_subcommands().flat_map(|subcommand| {
+        std::iter::once(subcommand.get_name())
+            .chain(subcommand.get_all_aliases())
+            .map(String::from)
+    });
+    let flags = cli.get_arguments().flat_map(|arg| {
+        let longs = arg.get_long().map(|long| format!("--{}", long));
+        let shorts = arg.get_short().map(|short| format!("-{}", short));
+        longs.into_iter().chain(shorts)
+    });
+    subcommands.chain(flags).collect()
+}
+
+fn is_reserved(name: &str) -> bool {
+    reserved_names().iter().any(|reserved| reserved == name)
+}
+
+// Categories can be named like a subcommand, but not run without `bsh run` or `bsh --`
+fn warn_if_reserved(category: &str) {
+    if is_reserved(category) {
+        eprintln!(
+            "{} '{}' is also a bsh command, run its commands with `bsh -- {} <ALIAS>`",
+            "Warning:".yellow().bold(),
+            category,
+            category
+        );
+    }
+}
+
+fn build_cli() -> Command {
+    Command::new("bsh")
         .version("0.1.0")
         .author("Matisse Callewaert")
         .about("Organizes and provides quick access to frequently used shell commands")
         .arg_required_else_help(true)
+        .arg(Arg::new("print")
+            .help("Opens the TUI and prints the picked command instead of running it")
+            .long("print")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("default-shell")
+            .help("Sets the shell or interpreter commands are run with when they do not set one, empty to use sh again")
+            .long("default-shell")
+            .value_name("SHELL"))
         .subcommand(
             Command::new("add")
                 .about("Adds a new command to a category or creates a new category if no command is given")
@@ -115,6 +737,34 @@ fn main() {
                 .arg(Arg::new("COMMAND")
                     .help("The command to add")
                     .required(false))
+                .arg(Arg::new("cwd")
+                    .help("The directory to run the command in, relative paths start from the root of the git repository")
+                    .long("cwd")
+                    .value_name("DIR"))
+                .arg(Arg::new("env")
+                    .help("Sets an environment variable for the command, or for all commands of the category when no command is given, can be given multiple times")
+                    .long("env")
+                    .value_name("NAME=VALUE")
+                    .value_parser(parse_var)
+                    .action(ArgAction::Append))
+                .arg(Arg::new("unset-env")
+                    .help("Removes an environment variable of the category, can be given multiple times")
+                    .long("unset-env")
+                    .value_name("NAME")
+                    .action(ArgAction::Append))
+                .arg(Arg::new("shell")
+                    .help("The shell or interpreter to run the command with (bash, zsh, fish, python3 or a command line the command is appended to), for all commands of the category when no command is given")
+                    .long("shell")
+                    .value_name("SHELL"))
+                .arg(Arg::new("desc")
+                    .help("A description of what the command does")
+                    .long("desc")
+                    .value_name("TEXT"))
+                .arg(Arg::new("tag")
+                    .help("Tags the command, can be given multiple times")
+                    .long("tag")
+                    .value_name("TAG")
+                    .action(ArgAction::Append))
         )
         .subcommand(
             Command::new("run")
@@ -126,6 +776,32 @@ fn main() {
                 .arg(Arg::new("ALIAS")
                     .help("The alias of the command to run")
                     .required(true))
+                .arg(Arg::new("VALUES")
+                    .help("Values for the placeholders of the command, in the order they appear, remaining values are passed as arguments. Options after the first value are passed on as well")
+                    .num_args(0..)
+                    .allow_hyphen_values(true))
+                .arg(Arg::new("ARGS")
+                    .help("Arguments passed to the command, available as $@, $1, ... or appended when it does not use them")
+                    .num_args(0..)
+                    .last(true))
+                .arg(Arg::new("var")
+                    .help("Sets the value of a placeholder, can be given multiple times")
+                    .long("var")
+                    .value_name("NAME=VALUE")
+                    .value_parser(parse_var)
+                    .action(ArgAction::Append))
+                .arg(Arg::new("no-input")
+                    .help("Fails instead of prompting when a placeholder has no value")
+                    .long("no-input")
+                    .action(ArgAction::SetTrue))
+                .arg(Arg::new("dry-run")
+                    .help("Prints the resolved command instead of running it")
+                    .long("dry-run")
+                    .action(ArgAction::SetTrue))
+                .arg(Arg::new("show-env")
+                    .help("Shows which placeholder values are taken from BSH_VAR_* environment variables")
+                    .long("show-env")
+                    .action(ArgAction::SetTrue))
         )
         .subcommand(
             Command::new("delete")
@@ -138,6 +814,44 @@ fn main() {
                     .help("The alias of the command to remove")
                     .required(false))
         )
+        .subcommand(
+            Command::new("rename")
+                .about("Renames a category, or a command of a category if a new name is given after its alias")
+                .override_usage("bsh rename <CATEGORY> [ALIAS] <NEW_NAME>")
+                .arg(Arg::new("CATEGORY")
+                    .help("The category to rename or rename the command in")
+                    .required(true))
+                .arg(Arg::new("ALIAS")
+                    .help("The alias of the command to rename, or the new name of the category")
+                    .required(true))
+                .arg(Arg::new("NEW_NAME")
+                    .help("The new alias of the command")
+                    .required(false))
+        )
+        .subcommand(
+            Command::new("mv")
+                .about("Moves a command to another category, creating the category if it does not exist")
+                .visible_alias("move")
+                .arg(Arg::new("SOURCE")
+                    .help("The command to move, as <CATEGORY>/<ALIAS>")
+                    .required(true))
+                .arg(Arg::new("DESTINATION")
+                    .help("The category to move the command to, as <CATEGORY> or <CATEGORY>/<NEW_ALIAS>")
+                    .required(true))
+                .arg(on_conflict_arg())
+        )
+        .subcommand(
+            Command::new("cp")
+                .about("Copies a command to another category, creating the category if it does not exist")
+                .visible_alias("copy")
+                .arg(Arg::new("SOURCE")
+                    .help("The command to copy, as <CATEGORY>/<ALIAS>")
+                    .required(true))
+                .arg(Arg::new("DESTINATION")
+                    .help("The category to copy the command to, as <CATEGORY> or <CATEGORY>/<NEW_ALIAS>")
+                    .required(true))
+                .arg(on_conflict_arg())
+        )
         .subcommand(
             Command::new("update")
                 .about("Updates a command of a category, if the category or command does not exist, it will be created")
@@ -149,88 +863,212 @@ fn main() {
                     .help("The alias of the command to update")
                     .required(true))
                 .arg(Arg::new("COMMAND")
-                    .help("The command to add")
-                    .required(true))
+                    .help("The new command, the stored one is kept when left out"))
+                .arg(Arg::new("cwd")
+                    .help("The directory to run the command in, relative paths start from the root of the git repository, empty to remove it")
+                    .long("cwd")
+                    .value_name("DIR"))
+                .arg(Arg::new("env")
+                    .help("Sets an environment variable for the command, can be given multiple times")
+                    .long("env")
+                    .value_name("NAME=VALUE")
+                    .value_parser(parse_var)
+                    .action(ArgAction::Append))
+                .arg(Arg::new("unset-env")
+                    .help("Removes an environment variable of the command, can be given multiple times")
+                    .long("unset-env")
+                    .value_name("NAME")
+                    .action(ArgAction::Append))
+                .arg(Arg::new("shell")
+                    .help("The shell or interpreter to run the command with (bash, zsh, fish, python3 or a command line the command is appended to), empty to remove it")
+                    .long("shell")
+                    .value_name("SHELL"))
+                .arg(Arg::new("desc")
+                    .help("A description of what the command does, empty to remove it")
+                    .long("desc")
+                    .value_name("TEXT"))
+                .arg(Arg::new("tag")
+                    .help("Replaces the tags of the command, can be given multiple times, empty to remove all tags")
+                    .long("tag")
+                    .value_name("TAG")
+                    .action(ArgAction::Append))
+        )
+        .subcommand(
+            Command::new("search")
+                .about("Searches categories, aliases, commands and descriptions, best matches first")
+                .arg(Arg::new("QUERY")
+                    .help("The text to search for, the characters only have to appear in order")
+                    .required(true)
+                    .num_args(1..))
+                .arg(Arg::new("run")
+                    .help("Runs the Nth result instead of listing them")
+                    .long("run")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(usize)))
         )
         .subcommand(
             Command::new("list")
                 .about("Lists all categories and their commands")
                 .alias("l")
-                .arg(Arg::new("category")
+                .arg(Arg::new("CATEGORY")
                     .help("Specify the category to list commands from")
                     .required(false))
+                .arg(Arg::new("sort")
+                    .help("The order to list the categories and commands in, instead of the one chosen in the TUI")
+                    .long("sort")
+                    .value_name("ORDER")
+                    .value_parser(SortMode::ALL.map(SortMode::name)))
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Prints a shell widget that inserts a command picked in the TUI into the command line")
+                .arg(Arg::new("SHELL")
+                    .help("The shell to print the widget for")
+                    .value_parser(["bash", "zsh", "fish"])
+                    .required(true))
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Prints a completion script for a shell, which completes categories and aliases too")
+                .arg(Arg::new("SHELL")
+                    .help("The shell to print the completion script for")
+                    .value_parser(["bash", "zsh", "fish"])
+                    .required(true))
         )
-        .get_matches_from(clap_args);
+        .subcommand(
+            Command::new("__complete")
+                .about("Prints the completions for a partial command line, used by the completion scripts")
+                .hide(true)
+                .arg(Arg::new("WORDS")
+                    .help("The words of the command line after bsh, the last one being completed")
+                    .num_args(0..)
+                    .last(true))
+        )
+}
 
-    let pathbuf = check_for_config_file_or_create();
-    let path = pathbuf.as_path();
+fn complete(words: &[String], config: &Config) -> Vec<String> {
+    let mut cli = build_cli();
+    cli.build();
 
-    let data = fs::read_to_string(path).expect("Unable to read file");
-    let mut config: Config = serde_json::from_str(&data).expect("Unable to parse JSON");
+    // `bsh -- <CATEGORY> <ALIAS>` is completed like `bsh run`, without the subcommands
+    let escaped = words.len() > 1 && words[0] == "--";
+    let words = if escaped { &words[1..] } else { words };
 
-    match matches.subcommand() {
-        Some(("add", sub_m)) => {
-            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
-            let alias = sub_m.get_one::<String>("ALIAS");
-            let command = sub_m.get_one::<String>("COMMAND");
+    let (current, previous) = match words.split_last() {
+        Some((current, previous)) => (current.as_str(), previous),
+        None => ("", words),
+    };
 
-            match (alias, command) {
-                (Some(alias), Some(command)) => {
-                    add_command(category, command, alias, &mut config, &path);
-                }
-                (None, None) => {
-                    add_category_to_config(category, &mut config, &path);
-                }
-                _ => {
-                    eprintln!("Error: When specifying an alias, a command must also be provided, and vice versa.");
-                }
-            }
-        }
-        Some(("run", sub_m)) => {
-            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
-            let alias = sub_m.get_one::<String>("ALIAS").unwrap();
-            run_command(category, alias, &config);
-        }
-        Some(("delete", sub_m)) => {
-            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
-            let alias = sub_m.get_one::<String>("ALIAS");
+    // Everything after `--` is passed on to the command
+    if previous.iter().any(|word| word == "--") {
+        return Vec::new();
+    }
 
-            match alias {
-                Some(alias) => {
-                    remove_command_from_config(category, alias, &mut config, &path);
-                }
-                None => {
-                    remove_category_from_config(category, &mut config, &path);
-                }
-            }
-        }
-        Some(("update", sub_m)) => {
-            let category = sub_m.get_one::<String>("CATEGORY").unwrap();
-            let alias = sub_m.get_one::<String>("ALIAS").unwrap();
-            let command = sub_m.get_one::<String>("COMMAND").unwrap();
+    let takes_value = |word: &str| {
+        std::iter::once(&cli)
+            .chain(cli.get_subcommands())
+            .flat_map(|command| command.get_arguments())
+            .any(|arg| {
+                arg.get_action().takes_values()
+                    && arg
+                        .get_long()
+                        .is_some_and(|long| word.strip_prefix("--") == Some(long))
+            })
+    };
 
-            update_command(category, command, alias, &mut config, &path);
-        }
-        Some(("list", sub_m)) => {
-            handle_list_command(sub_m, &config);
+    let mut positionals: Vec<&str> = Vec::new();
+    let mut skip_value = false;
+    for word in previous {
+        if skip_value {
+            skip_value = false;
+        } else if word.starts_with('-') {
+            skip_value = takes_value(word);
+        } else {
+            positionals.push(word);
         }
-        _ => {}
     }
-}
+
+    // `bsh <CATEGORY> <ALIAS>` is completed like `bsh run <CATEGORY> <ALIAS>`
+    let run = cli.find_subcommand("run").unwrap();
+    let (command, given) = match positionals.split_first() {
+        _ if escaped => (run, &positionals[..]),
+        Some((first, rest)) => match cli.find_subcommand(first) {
+            Some(subcommand) => (subcommand, rest),
+            None => (run, &positionals[..]),
+        },
+        None => (&cli, &positionals[..]),
+    };
+
+    let mut candidates: Vec<String> = if current.starts_with('-') {
+        command
+            .get_arguments()
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{}", long))
+            .collect()
+    } else if positionals.is_empty() && !escaped {
+        cli.get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| subcommand.get_name().to_string())
+            .chain(config.categories.keys().cloned())
+            .collect()
+    } else {
+        match command.get_positionals().nth(given.len()) {
+            Some(arg) if arg.get_id() == "CATEGORY" => config.categories.keys().cloned().collect(),
+            Some(arg) if arg.get_id() == "SOURCE" => config
+                .categories
+                .iter()
+                .flat_map(|(category, commands)| {
+                    commands
+                        .keys()
+                        .map(move |alias| format!("{}/{}", category, alias))
+                })
+                .collect(),
+            Some(arg) if arg.get_id() == "DESTINATION" => {
+                config.categories.keys().cloned().collect()
+            }
+            Some(arg) if arg.get_id() == "ALIAS" => config
+                .categories
+                .get(given[0])
+                .map(|commands| commands.keys().cloned().collect())
+                .unwrap_or_default(),
+            Some(arg) => arg
+                .get_possible_values()
+                .iter()
+                .map(|value| value.get_name().to_string())
+                .collect(),
+            None => Vec::new(),
+        }
+    };
+
+    candidates.retain(|candidate| candidate.starts_with(current));
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
 
 fn handle_list_command(matches: &ArgMatches, config: &Config) {
-    if let Some(category) = matches.get_one::<String>("category") {
-        list_all_commands_with_aliases_in_category(category, config);
+    let sort = matches
+        .get_one::<String>("sort")
+        .and_then(|name| SortMode::from_name(name))
+        .unwrap_or(config.sort);
+    if let Some(category) = matches.get_one::<String>("CATEGORY") {
+        list_all_commands_with_aliases_in_category(category, config, sort);
     } else {
-        list_all_commands_with_aliases(config);
+        list_all_commands_with_aliases(config, sort);
     }
 }
 
-fn start_tui() -> Result<(), io::Error> {
+// With `print` the picked command is printed instead of run, the TUI is then drawn on the
+// terminal directly so stdout can be captured by a shell widget
+fn start_tui(print: bool) -> Result<i32, io::Error> {
     enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    let mut output: Box<dyn Write> = if print {
+        Box::new(fs::OpenOptions::new().write(true).open("/dev/tty")?)
+    } else {
+        Box::new(io::stdout())
+    };
+    execute!(output, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(output);
     let mut terminal = Terminal::new(backend)?;
 
     let mut app_state = AppState::default();
@@ -240,17 +1078,12 @@ fn start_tui() -> Result<(), io::Error> {
     let data = fs::read_to_string(path).expect("Unable to read file");
     let mut config: Config = serde_json::from_str(&data).expect("Unable to parse JSON");
 
-    app_state.categories = config.categories.keys().cloned().collect();
-    for (category, commands) in &config.categories {
-        let cmd_list: Vec<(String, String)> = commands
-            .iter()
-            .map(|(alias, cmd)| (alias.clone(), cmd.clone()))
-            .collect();
-        app_state.commands.insert(category.clone(), cmd_list);
-    }
+    load_lists(&mut app_state, &config);
 
     let mut category_state = ListState::default();
     let mut command_state = ListState::default();
+    let mut filter_state = ListState::default();
+    let mut exit_code = 0;
     category_state.select(Some(0));
     app_state.selected_category = Some(0);
 
@@ -264,7 +1097,7 @@ fn start_tui() -> Result<(), io::Error> {
                 .margin(1)
                 .constraints(
                     [
-                        Constraint::Length(6), // Fixed height for the logo chunk
+                        Constraint::Length(7), // Fixed height for the logo chunk
                         Constraint::Min(0),    // Remaining space for categories and commands
                     ]
                     .as_ref(),
@@ -276,8 +1109,9 @@ fn start_tui() -> Result<(), io::Error> {
                 .direction(Direction::Horizontal)
                 .constraints(
                     [
-                        Constraint::Percentage(50), // Space for logo
-                        Constraint::Percentage(50), // Space for controls
+                        Constraint::Percentage(40), // Space for logo
+                        Constraint::Percentage(30), // Space for controls
+                        Constraint::Percentage(30), // Space for command controls
                     ]
                     .as_ref(),
                 )
@@ -346,6 +1180,12 @@ fn start_tui() -> Result<(), io::Error> {
                         .fg(Color::White)
                         .add_modifier(Modifier::BOLD),
                 )),
+                Spans::from(Span::styled(
+                    "Shift+↑/↓ - Move up or down",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
                 Spans::from(Span::styled(
                     "d - Delete (Only on categories)",
                     Style::default()
@@ -358,11 +1198,60 @@ fn start_tui() -> Result<(), io::Error> {
                         .fg(Color::White)
                         .add_modifier(Modifier::BOLD),
                 )),
+                Spans::from(Span::styled(
+                    "/ - Filter all commands",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
             ])
             .block(Block::default().borders(Borders::NONE))
             .style(Style::default().add_modifier(Modifier::BOLD));
             f.render_widget(controls_paragraph, logo_and_controls[1]);
 
+            // Render the controls that act on the selected command next to them
+            let command_controls_paragraph = Paragraph::new(vec![
+                Spans::from(Span::styled(
+                    "More:",
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::styled(
+                    format!("s - Sort order ({})", config.sort.name()),
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::styled(
+                    "p - Print command instead of running it",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::styled(
+                    "m - Move command to another category",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::styled(
+                    "r - Rename",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                Spans::from(Span::styled(
+                    "w - Set working directory",
+                    Style::default()
+                        .fg(Color::White)
+                        .add_modifier(Modifier::BOLD),
+                )),
+            ])
+            .block(Block::default().borders(Borders::NONE))
+            .style(Style::default().add_modifier(Modifier::BOLD));
+            f.render_widget(command_controls_paragraph, logo_and_controls[2]);
+
             // Split the bottom chunk into horizontal chunks
             let horizontal_chunks = Layout::default()
                 .direction(Direction::Horizontal)
@@ -455,8 +1344,8 @@ fn start_tui() -> Result<(), io::Error> {
                         let command_list: Vec<ListItem> = commands
                             .iter()
                             .enumerate()
-                            .map(|(i, (alias, command))| {
-                                let content = if app_state.selected_command == Some(i) {
+                            .map(|(i, (alias, entry))| {
+                                let mut content = if app_state.selected_command == Some(i) {
                                     Spans::from(vec![
                                         Span::styled("> ", Style::default().fg(Color::Yellow)),
                                         Span::styled(
@@ -466,7 +1355,7 @@ fn start_tui() -> Result<(), io::Error> {
                                                 .add_modifier(Modifier::BOLD),
                                         ),
                                         Span::styled(
-                                            format!(": {}", command),
+                                            format!(": {}", entry.command),
                                             Style::default().add_modifier(Modifier::BOLD),
                                         ),
                                     ])
@@ -477,10 +1366,45 @@ fn start_tui() -> Result<(), io::Error> {
                                             alias.clone(),
                                             Style::default().fg(Color::Green),
                                         ),
-                                        Span::raw(format!(": {}", command)),
+                                        Span::raw(format!(": {}", entry.command)),
                                     ])
                                 };
-                                ListItem::new(content)
+                                if let Some(cwd) = &entry.cwd {
+                                    content.0.push(Span::styled(
+                                        format!("  (in {})", cwd),
+                                        Style::default().fg(Color::DarkGray),
+                                    ));
+                                }
+                                if !entry.env.is_empty() {
+                                    content.0.push(Span::styled(
+                                        format!("  (env {})", env_names(&entry.env)),
+                                        Style::default().fg(Color::DarkGray),
+                                    ));
+                                }
+                                if let Some(shell) = &entry.shell {
+                                    content.0.push(Span::styled(
+                                        format!("  (shell {})", shell),
+                                        Style::default().fg(Color::DarkGray),
+                                    ));
+                                }
+                                for tag in &entry.tags {
+                                    content.0.push(Span::styled(
+                                        format!("  #{}", tag),
+                                        Style::default().fg(Color::Cyan),
+                                    ));
+                                }
+                                match &entry.description {
+                                    Some(description) => ListItem::new(vec![
+                                        content,
+                                        Spans::from(Span::styled(
+                                            format!("    {}", description),
+                                            Style::default()
+                                                .fg(Color::DarkGray)
+                                                .add_modifier(Modifier::ITALIC),
+                                        )),
+                                    ]),
+                                    None => ListItem::new(content),
+                                }
                             })
                             .collect();
 
@@ -519,6 +1443,16 @@ fn start_tui() -> Result<(), io::Error> {
                         // Define a fixed height for each button
                         let button_height = 1; // Adjust this value to match the height of the list item text
 
+                        // Commands with a description take two rows, the buttons stay next to the first
+                        let button_rows: Vec<u16> = commands
+                            .iter()
+                            .scan(0, |row, (_, entry)| {
+                                let button_row = *row;
+                                *row += if entry.description.is_some() { 2 } else { 1 };
+                                Some(button_row)
+                            })
+                            .collect();
+
                         // Render update buttons for each command
                         let update_buttons: Vec<Paragraph> = commands
                             .iter()
@@ -543,11 +1477,13 @@ fn start_tui() -> Result<(), io::Error> {
 
                         // Render update buttons for each command
                         for (i, button) in update_buttons.into_iter().enumerate() {
+                            let y = horizontal_chunks[2].y + button_rows[i] + button_height;
+                            if y + button_height >= horizontal_chunks[2].bottom() {
+                                break;
+                            }
                             let button_area = tui::layout::Rect {
                                 x: horizontal_chunks[2].x,
-                                y: horizontal_chunks[2].y
-                                    + (i as u16 * button_height)
-                                    + button_height,
+                                y,
                                 width: horizontal_chunks[2].width,
                                 height: button_height,
                             };
@@ -575,11 +1511,13 @@ fn start_tui() -> Result<(), io::Error> {
                             .collect();
 
                         for (i, button) in delete_buttons.into_iter().enumerate() {
+                            let y = horizontal_chunks[3].y + button_rows[i] + button_height;
+                            if y + button_height >= horizontal_chunks[3].bottom() {
+                                break;
+                            }
                             let button_area = tui::layout::Rect {
                                 x: horizontal_chunks[3].x,
-                                y: horizontal_chunks[3].y
-                                    + (i as u16 * button_height)
-                                    + button_height,
+                                y,
                                 width: horizontal_chunks[3].width,
                                 height: button_height,
                             };
@@ -589,10 +1527,132 @@ fn start_tui() -> Result<(), io::Error> {
                 }
             }
 
+            // The filter replaces both lists with the matching commands of all categories
+            if app_state.input_mode == InputMode::Filtering {
+                let filter_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+                    .split(vertical_chunks[1]);
+
+                let filter_input = Paragraph::new(Spans::from(vec![
+                    Span::styled("/", Style::default().fg(Color::Yellow)),
+                    Span::raw(app_state.input.clone()),
+                ]))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(
+                            Style::default()
+                                .fg(Color::Yellow)
+                                .add_modifier(Modifier::BOLD),
+                        )
+                        .title(Spans::from(Span::styled(
+                            "Filter",
+                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        ))),
+                );
+
+                let results = search_commands(&app_state.input, &config);
+                let result_list: Vec<ListItem> = results
+                    .iter()
+                    .map(|result| {
+                        let entry = &config.categories[&result.category][&result.alias];
+                        let mut spans = highlight_spans(
+                            &result.category,
+                            &result.category_matches,
+                            Style::default().fg(Color::Red),
+                        );
+                        spans.push(Span::raw(" › "));
+                        spans.extend(highlight_spans(
+                            &result.alias,
+                            &result.alias_matches,
+                            Style::default().fg(Color::Green),
+                        ));
+                        spans.push(Span::raw(": "));
+                        spans.extend(highlight_spans(
+                            &entry.command,
+                            &result.command_matches,
+                            Style::default(),
+                        ));
+                        ListItem::new(Spans::from(spans))
+                    })
+                    .collect();
+                filter_state
+                    .select(Some(app_state.selected_choice).filter(|_| !results.is_empty()));
+
+                let results_box = List::new(result_list)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Blue))
+                            .title(Spans::from(Span::styled(
+                                format!("{} matching commands", results.len()),
+                                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                            ))),
+                    )
+                    .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                    .highlight_symbol("> ");
+
+                f.render_widget(Clear, vertical_chunks[1]);
+                f.render_widget(filter_input, filter_chunks[0]);
+                f.render_stateful_widget(results_box, filter_chunks[1], &mut filter_state);
+                f.set_cursor(
+                    filter_chunks[0].x + app_state.input.chars().count() as u16 + 2,
+                    filter_chunks[0].y + 1,
+                );
+            }
+
             if app_state.input_mode == InputMode::Editing
                 || app_state.input_mode == InputMode::Adding
+                || app_state.input_mode == InputMode::Placeholder
+                || app_state.input_mode == InputMode::WorkingDirectory
+                || app_state.input_mode == InputMode::Renaming
+                || app_state.input_mode == InputMode::Moving
             {
-                let title = if app_state.input_mode == InputMode::Editing {
+                let title = if let Some(error) = &app_state.input_error {
+                    Spans::from(Span::styled(
+                        error.clone(),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ))
+                } else if app_state.input_mode == InputMode::Moving {
+                    let category = &app_state.categories[app_state.selected_category.unwrap()];
+                    Spans::from(Span::styled(
+                        format!(
+                            "Move {} to",
+                            app_state.commands[category][app_state.selected_command.unwrap()].0
+                        ),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ))
+                } else if app_state.input_mode == InputMode::Renaming {
+                    let category = &app_state.categories[app_state.selected_category.unwrap()];
+                    let name = if app_state.mode == Mode::Category {
+                        format!("Rename category {}", category)
+                    } else {
+                        format!(
+                            "Rename alias {}",
+                            app_state.commands[category][app_state.selected_command.unwrap()].0
+                        )
+                    };
+                    Spans::from(Span::styled(
+                        name,
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ))
+                } else if app_state.input_mode == InputMode::WorkingDirectory {
+                    let category = &app_state.categories[app_state.selected_category.unwrap()];
+                    let alias = &app_state.commands.get(category).unwrap()
+                        [app_state.selected_command.unwrap()]
+                    .0;
+                    Spans::from(Span::styled(
+                        format!("Working directory for {}", alias),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ))
+                } else if app_state.input_mode == InputMode::Placeholder {
+                    let placeholder = &app_state.placeholders[app_state.placeholder_values.len()];
+                    Spans::from(Span::styled(
+                        format!("Value for {}", placeholder.name),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ))
+                } else if app_state.input_mode == InputMode::Editing {
                     if app_state.mode == Mode::Category {
                         Spans::from(Span::styled(
                             "Update Category",
@@ -642,6 +1702,10 @@ fn start_tui() -> Result<(), io::Error> {
                                 Span::styled("command", Style::default().fg(Color::DarkGray)),
                             ])
                         }
+                    } else if app_state.input_mode == InputMode::Placeholder
+                        && app_state.placeholders[app_state.placeholder_values.len()].secret
+                    {
+                        Spans::from("*".repeat(app_state.input.chars().count()))
                     } else {
                         Spans::from(app_state.input.as_ref())
                     };
@@ -654,7 +1718,7 @@ fn start_tui() -> Result<(), io::Error> {
                                 .fg(Color::Yellow)
                                 .add_modifier(Modifier::BOLD),
                         )
-                        .title(title),
+                        .title(title.clone()),
                 );
 
                 let area = if app_state.mode == Mode::Category {
@@ -673,13 +1737,62 @@ fn start_tui() -> Result<(), io::Error> {
                     )
                 };
 
+                let choices = if app_state.input_mode == InputMode::Placeholder {
+                    &app_state.placeholders[app_state.placeholder_values.len()].choices
+                } else if app_state.input_mode == InputMode::Moving {
+                    &app_state.move_targets
+                } else {
+                    &Vec::new()
+                };
+
                 f.render_widget(Clear, area);
-                f.render_widget(input_box, area);
-                f.set_cursor(area.x + app_state.input.len() as u16 + 1, area.y + 1);
+                if choices.is_empty() {
+                    f.render_widget(input_box, area);
+                    f.set_cursor(area.x + app_state.input.len() as u16 + 1, area.y + 1);
+                } else {
+                    let choice_list: Vec<ListItem> = choices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, choice)| {
+                            if app_state.selected_choice == i {
+                                ListItem::new(Spans::from(vec![
+                                    Span::styled("> ", Style::default().fg(Color::Yellow)),
+                                    Span::styled(
+                                        choice.clone(),
+                                        Style::default()
+                                            .fg(Color::Green)
+                                            .add_modifier(Modifier::BOLD),
+                                    ),
+                                ]))
+                            } else {
+                                ListItem::new(Spans::from(vec![
+                                    Span::raw("  "),
+                                    Span::raw(choice.clone()),
+                                ]))
+                            }
+                        })
+                        .collect();
+
+                    let choice_box = List::new(choice_list).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(
+                                Style::default()
+                                    .fg(Color::Yellow)
+                                    .add_modifier(Modifier::BOLD),
+                            )
+                            .title(title),
+                    );
+                    f.render_widget(choice_box, area);
+                }
             }
         })?;
 
-        if let Event::Key(key) = event::read()? {
+        let event = match app_state.queued_key.take() {
+            Some(key) => Event::Key(key),
+            None => event::read()?,
+        };
+        if let Event::Key(key) = event {
             match app_state.input_mode {
                 InputMode::Normal => match app_state.mode {
                     Mode::Category => match key.code {
@@ -689,6 +1802,32 @@ fn start_tui() -> Result<(), io::Error> {
                             terminal.show_cursor()?;
                             break;
                         }
+                        KeyCode::Up | KeyCode::Down
+                            if key.modifiers.contains(KeyModifiers::SHIFT) =>
+                        {
+                            move_selected(
+                                &mut app_state,
+                                &mut config,
+                                path,
+                                key.code == KeyCode::Up,
+                            );
+                            category_state.select(app_state.selected_category);
+                        }
+                        KeyCode::Char('s') => {
+                            cycle_sort_mode(&mut app_state, &mut config, path);
+                            category_state.select(app_state.selected_category);
+                        }
+                        KeyCode::Char('/') => {
+                            app_state.input.clear();
+                            app_state.selected_choice = 0;
+                            app_state.input_mode = InputMode::Filtering;
+                        }
+                        KeyCode::Char('r') => {
+                            if let Some(selected) = app_state.selected_category {
+                                app_state.input = app_state.categories[selected].clone();
+                                app_state.input_mode = InputMode::Renaming;
+                            }
+                        }
                         KeyCode::Up => {
                             if let Some(selected) = category_state.selected() {
                                 if selected > 0 {
@@ -711,12 +1850,10 @@ fn start_tui() -> Result<(), io::Error> {
                                 }
                             }
                         }
-                        KeyCode::Enter | KeyCode::Right => {
-                            if !app_state.categories.is_empty() {
-                                app_state.mode = Mode::Command;
-                                command_state.select(Some(0));
-                                app_state.selected_command = Some(0);
-                            }
+                        KeyCode::Enter | KeyCode::Right if !app_state.categories.is_empty() => {
+                            app_state.mode = Mode::Command;
+                            command_state.select(Some(0));
+                            app_state.selected_command = Some(0);
                         }
                         KeyCode::Char('d') => {
                             if let Some(selected) = category_state.selected() {
@@ -747,6 +1884,57 @@ fn start_tui() -> Result<(), io::Error> {
                             terminal.show_cursor()?;
                             break;
                         }
+                        KeyCode::Up | KeyCode::Down
+                            if key.modifiers.contains(KeyModifiers::SHIFT) =>
+                        {
+                            move_selected(
+                                &mut app_state,
+                                &mut config,
+                                path,
+                                key.code == KeyCode::Up,
+                            );
+                            command_state.select(app_state.selected_command);
+                        }
+                        KeyCode::Char('s') => {
+                            cycle_sort_mode(&mut app_state, &mut config, path);
+                            category_state.select(app_state.selected_category);
+                            command_state.select(app_state.selected_command);
+                        }
+                        KeyCode::Char('/') => {
+                            app_state.input.clear();
+                            app_state.selected_choice = 0;
+                            app_state.input_mode = InputMode::Filtering;
+                        }
+                        KeyCode::Char('r') => {
+                            let selected = app_state.selected_category.and_then(|category| {
+                                let commands =
+                                    app_state.commands.get(&app_state.categories[category])?;
+                                commands.get(app_state.selected_command?)
+                            });
+                            if let Some((alias, _)) = selected {
+                                app_state.input = alias.clone();
+                                app_state.input_mode = InputMode::Renaming;
+                            }
+                        }
+                        KeyCode::Char('m') => {
+                            let category_index = app_state.selected_category.unwrap();
+                            let has_command = app_state.selected_command.is_some_and(|selected| {
+                                selected
+                                    < app_state.commands[&app_state.categories[category_index]]
+                                        .len()
+                            });
+                            app_state.move_targets = app_state
+                                .categories
+                                .iter()
+                                .enumerate()
+                                .filter(|(i, _)| *i != category_index)
+                                .map(|(_, category)| category.clone())
+                                .collect();
+                            if has_command && !app_state.move_targets.is_empty() {
+                                app_state.selected_choice = 0;
+                                app_state.input_mode = InputMode::Moving;
+                            }
+                        }
                         KeyCode::Up => {
                             if let Some(selected) = command_state.selected() {
                                 if selected > 0 {
@@ -788,26 +1976,74 @@ fn start_tui() -> Result<(), io::Error> {
                                 }
                             }
                         }
-                        KeyCode::Enter => {
-                            disable_raw_mode()?;
-                            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                            terminal.show_cursor()?;
-
-                            if let Some(selected_category) = app_state.selected_category {
-                                if let Some(commands) = app_state
+                        KeyCode::Char('w') => {
+                            let selected = app_state.selected_category.and_then(|category| {
+                                let commands =
+                                    app_state.commands.get(&app_state.categories[category])?;
+                                commands.get(app_state.selected_command?)
+                            });
+                            if let Some((_, entry)) = selected {
+                                app_state.input = entry.cwd.clone().unwrap_or_default();
+                                app_state.input_mode = InputMode::WorkingDirectory;
+                            }
+                        }
+                        KeyCode::Enter | KeyCode::Char('p') => {
+                            app_state.dry_run = print || key.code == KeyCode::Char('p');
+                            let selected = app_state.selected_category.and_then(|category| {
+                                let category = &app_state.categories[category];
+                                let (alias, entry) = app_state
                                     .commands
-                                    .get(&app_state.categories[selected_category])
+                                    .get(category)?
+                                    .get(app_state.selected_command?)?;
+                                Some((alias, with_category_settings(category, entry, &config)))
+                            });
+
+                            let placeholders = match &selected {
+                                Some((_, entry)) => find_entry_placeholders(entry),
+                                None => Ok(Vec::new()),
+                            };
+                            let cwd = match &selected {
+                                Some((_, entry)) => entry
+                                    .cwd
+                                    .as_deref()
+                                    .map(resolve_working_directory)
+                                    .transpose(),
+                                None => Ok(None),
+                            };
+
+                            // Errors are reported by `run_command` once the TUI is closed
+                            match (&selected, placeholders, cwd) {
+                                (Some((_, entry)), Ok(placeholders), Ok(cwd))
+                                    if !placeholders.is_empty() =>
                                 {
-                                    if let Some(selected_command) = app_state.selected_command {
-                                        run_command(
-                                            &app_state.categories[selected_category],
-                                            &commands[selected_command].0,
-                                            &config,
+                                    app_state.pending_command = entry.clone();
+                                    app_state.pending_cwd = cwd;
+                                    app_state.placeholders = placeholders;
+                                    app_state.placeholder_values.clear();
+                                    app_state.input_mode = InputMode::Placeholder;
+                                    start_placeholder_input(&mut app_state);
+                                }
+                                _ => {
+                                    disable_raw_mode()?;
+                                    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+                                    terminal.show_cursor()?;
+
+                                    if let Some((alias, _)) = selected {
+                                        exit_code = run_command(
+                                            &app_state.categories
+                                                [app_state.selected_category.unwrap()],
+                                            alias,
+                                            &mut config,
+                                            path,
+                                            &RunOptions {
+                                                dry_run: app_state.dry_run,
+                                                ..Default::default()
+                                            },
                                         );
                                     }
+                                    break;
                                 }
                             }
-                            break;
                         }
                         _ => {}
                     },
@@ -840,11 +2076,11 @@ fn start_tui() -> Result<(), io::Error> {
                                     if let Some(selected_command) = app_state.selected_command {
                                         let category_index = app_state.selected_category.unwrap();
                                         let category = &app_state.categories[category_index];
-                                        let command = &app_state.commands.get(category).unwrap()
+                                        let entry = &app_state.commands.get(category).unwrap()
                                             [selected_command]
                                             .1;
 
-                                        app_state.input = command.clone();
+                                        app_state.input = entry.command.clone();
                                         app_state.input_mode = InputMode::Editing;
                                     }
                                 } else if selected == 1 {
@@ -863,7 +2099,7 @@ fn start_tui() -> Result<(), io::Error> {
                                         if let Some(category_commands) =
                                             config.categories.get_mut(category)
                                         {
-                                            category_commands.remove(&alias);
+                                            category_commands.shift_remove(&alias);
                                         }
 
                                         update_config_file(&config, path);
@@ -883,11 +2119,10 @@ fn start_tui() -> Result<(), io::Error> {
                         if let Some(selected_command) = app_state.selected_command {
                             let category =
                                 &app_state.categories[app_state.selected_category.unwrap()];
-                            let alias =
-                                &app_state.commands.get(category).unwrap()[selected_command].0;
-                            update_command(category, &app_state.input, alias, &mut config, path);
-                            app_state.commands.get_mut(category).unwrap()[selected_command].1 =
-                                app_state.input.clone();
+                            let (alias, entry) = &mut app_state.commands.get_mut(category).unwrap()
+                                [selected_command];
+                            entry.command = app_state.input.clone();
+                            update_command(category, entry, alias, &mut config, path);
                             update_config_file(&config, path);
                             app_state.input_mode = InputMode::Normal;
                             app_state.input.clear();
@@ -905,63 +2140,58 @@ fn start_tui() -> Result<(), io::Error> {
                     }
                     _ => {}
                 },
-                InputMode::Adding => match key.code {
+                InputMode::Placeholder => match key.code {
                     KeyCode::Enter => {
-                        if app_state.mode == Mode::Category && !app_state.input.is_empty() {
-                            let category = app_state.input.clone();
-                            if !app_state.categories.contains(&category) {
-                                add_category_to_config(&category, &mut config, path);
-                                app_state.categories.push(category.clone());
-                                app_state.commands.insert(category, Vec::new());
-                                update_config_file(&config, path);
-                                app_state.input_mode = InputMode::Normal;
-                                app_state.input.clear();
-                            }
-                        } else if app_state.mode == Mode::Command && !app_state.input.is_empty() {
-                            let parts: Vec<&str> = app_state.input.splitn(2, ' ').collect();
-                            if parts.len() == 2 {
-                                let alias = parts[0].to_string();
-                                let command = parts[1].to_string();
-
-                                let category_index = app_state.selected_category.unwrap();
-                                let category = &app_state.categories[category_index];
-
-                                let alias_exists = config
-                                    .categories
-                                    .get(category)
-                                    .map_or(false, |cmds| cmds.contains_key(&alias));
-                                let category_exists = config.categories.contains_key(category);
-
-                                if !alias_exists {
-                                    if let Some(commands) = config.categories.get_mut(category) {
-                                        commands.insert(alias.clone(), command.clone());
-                                    } else {
-                                        let mut new_commands = HashMap::new();
-                                        if !category_exists {
-                                            new_commands.insert(alias.clone(), command.clone());
-                                            config
-                                                .categories
-                                                .insert(category.clone(), new_commands);
-                                        }
-                                    }
+                        let placeholder =
+                            &app_state.placeholders[app_state.placeholder_values.len()];
+                        let value = if !placeholder.choices.is_empty() {
+                            placeholder.choices[app_state.selected_choice].clone()
+                        } else if app_state.input.is_empty() {
+                            placeholder.default.clone().unwrap_or_default()
+                        } else {
+                            app_state.input.clone()
+                        };
+                        app_state.placeholder_values.push(value);
 
-                                    app_state
-                                        .commands
-                                        .entry(category.clone())
-                                        .or_insert_with(Vec::new)
-                                        .push((alias.clone(), command.clone()));
+                        if app_state.placeholder_values.len() < app_state.placeholders.len() {
+                            start_placeholder_input(&mut app_state);
+                        } else {
+                            disable_raw_mode()?;
+                            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+                            terminal.show_cursor()?;
 
-                                    update_config_file(&config, path);
-                                    app_state.mode = Mode::Command;
-                                    app_state.input_mode = InputMode::Normal;
-                                    app_state.input.clear();
-                                }
-                            }
+                            let category =
+                                &app_state.categories[app_state.selected_category.unwrap()];
+                            let alias = &app_state.commands[category]
+                                [app_state.selected_command.unwrap()]
+                            .0;
+                            exit_code = run_resolved_command(
+                                &app_state.pending_command,
+                                app_state.pending_cwd.as_deref(),
+                                &app_state.placeholders,
+                                &app_state.placeholder_values,
+                                &[],
+                                app_state.dry_run,
+                                || record_use(category, alias, &mut config, path),
+                            );
+                            break;
                         }
                     }
-                    KeyCode::Esc | KeyCode::Up => {
+                    KeyCode::Esc => {
                         app_state.input_mode = InputMode::Normal;
                         app_state.input.clear();
+                        app_state.placeholders.clear();
+                        app_state.placeholder_values.clear();
+                    }
+                    KeyCode::Up if app_state.selected_choice > 0 => {
+                        app_state.selected_choice -= 1;
+                    }
+                    KeyCode::Down => {
+                        let placeholder =
+                            &app_state.placeholders[app_state.placeholder_values.len()];
+                        if app_state.selected_choice + 1 < placeholder.choices.len() {
+                            app_state.selected_choice += 1;
+                        }
                     }
                     KeyCode::Char(c) => {
                         app_state.input.push(c);
@@ -971,183 +2201,1578 @@ fn start_tui() -> Result<(), io::Error> {
                     }
                     _ => {}
                 },
-            }
-        }
-    }
-
-    Ok(())
-}
-
-fn add_command(category: &str, command: &str, alias: &str, config: &mut Config, path: &Path) {
-    if !check_if_category_exists(category, config) {
-        println!("Adding Category '{}', because it does not exist", category);
-        add_category_to_config(category, config, path);
-    }
-    if check_if_command_exists(category, alias, config) {
-        println!("Command '{}' already exists in category '{}', if you want to update the command, use update", command, category);
-    } else {
-        println!("Adding command '{}' to category '{}'", command, category);
-        add_command_to_config(category, command, alias, config, path);
-    }
-}
+                InputMode::Filtering => match key.code {
+                    KeyCode::Enter => {
+                        let results = search_commands(&app_state.input, &config);
+                        if let Some(result) = results.get(app_state.selected_choice) {
+                            // Select the hit and run it like Enter on the command list would
+                            app_state.selected_category = app_state
+                                .categories
+                                .iter()
+                                .position(|category| *category == result.category);
+                            app_state.selected_command = app_state.commands[&result.category]
+                                .iter()
+                                .position(|(alias, _)| *alias == result.alias);
+                            category_state.select(app_state.selected_category);
+                            command_state.select(app_state.selected_command);
+                            app_state.mode = Mode::Command;
+                            app_state.input_mode = InputMode::Normal;
+                            app_state.input.clear();
+                            app_state.queued_key = Some(KeyEvent::from(KeyCode::Enter));
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app_state.input_mode = InputMode::Normal;
+                        app_state.input.clear();
+                    }
+                    KeyCode::Up if app_state.selected_choice > 0 => {
+                        app_state.selected_choice -= 1;
+                    }
+                    KeyCode::Down => {
+                        let results = search_commands(&app_state.input, &config);
+                        if app_state.selected_choice + 1 < results.len() {
+                            app_state.selected_choice += 1;
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        app_state.input.push(c);
+                        app_state.selected_choice = 0;
+                    }
+                    KeyCode::Backspace => {
+                        app_state.input.pop();
+                        app_state.selected_choice = 0;
+                    }
+                    _ => {}
+                },
+                InputMode::Moving => match key.code {
+                    KeyCode::Enter => {
+                        let category =
+                            app_state.categories[app_state.selected_category.unwrap()].clone();
+                        let alias = app_state.commands[&category]
+                            [app_state.selected_command.unwrap()]
+                        .0
+                        .clone();
+                        let target = &app_state.move_targets[app_state.selected_choice];
 
-fn update_command(category: &str, command: &str, alias: &str, config: &mut Config, path: &Path) {
-    if !check_if_category_exists(category, config) {
-        println!("Adding Category '{}', because it does not exist", category);
-        add_category_to_config(category, config, path);
-    }
-    if check_if_command_exists(category, alias, config) {
-        update_command_in_config(category, command, alias, config, path);
+                        match transfer_command(
+                            &category,
+                            &alias,
+                            target,
+                            &alias,
+                            OnConflict::Fail,
+                            false,
+                            &mut config,
+                        ) {
+                            Ok(_) => {
+                                update_config_file(&config, path);
+                                load_lists(&mut app_state, &config);
+                                app_state.selected_category =
+                                    app_state.categories.iter().position(|c| *c == category);
+                                category_state.select(app_state.selected_category);
+                                command_state.select(Some(0));
+                                app_state.selected_command = Some(0);
+                                app_state.input_mode = InputMode::Normal;
+                            }
+                            Err(e) => app_state.input_error = Some(e),
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app_state.input_mode = InputMode::Normal;
+                        app_state.input_error = None;
+                    }
+                    KeyCode::Up if app_state.selected_choice > 0 => {
+                        app_state.selected_choice -= 1;
+                        app_state.input_error = None;
+                    }
+                    KeyCode::Down
+                        if app_state.selected_choice + 1 < app_state.move_targets.len() =>
+                    {
+                        app_state.selected_choice += 1;
+                        app_state.input_error = None;
+                    }
+                    _ => {}
+                },
+                InputMode::Renaming => match key.code {
+                    KeyCode::Enter if !app_state.input.is_empty() => {
+                        let category_index = app_state.selected_category.unwrap();
+                        let category = app_state.categories[category_index].clone();
+                        let new_name = app_state.input.clone();
+                        let result = if app_state.mode == Mode::Category {
+                            if new_name == category {
+                                Ok(())
+                            } else if is_reserved(&new_name) && app_state.input_error.is_none() {
+                                Err(RESERVED_WARNING.to_string())
+                            } else {
+                                rename_category(&category, &new_name, &mut config).map(|()| {
+                                    let commands =
+                                        app_state.commands.remove(&category).unwrap_or_default();
+                                    app_state.commands.insert(new_name.clone(), commands);
+                                    app_state.categories[category_index] = new_name;
+                                })
+                            }
+                        } else {
+                            let selected_command = app_state.selected_command.unwrap();
+                            let alias = &mut app_state.commands.get_mut(&category).unwrap()
+                                [selected_command]
+                                .0;
+                            if new_name == *alias {
+                                Ok(())
+                            } else {
+                                rename_command(&category, alias, &new_name, &mut config)
+                                    .map(|()| *alias = new_name)
+                            }
+                        };
+
+                        match result {
+                            Ok(()) => {
+                                update_config_file(&config, path);
+                                app_state.input_mode = InputMode::Normal;
+                                app_state.input.clear();
+                                app_state.input_error = None;
+                            }
+                            Err(e) => app_state.input_error = Some(e),
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app_state.input_mode = InputMode::Normal;
+                        app_state.input.clear();
+                        app_state.input_error = None;
+                    }
+                    KeyCode::Char(c) => {
+                        app_state.input.push(c);
+                        app_state.input_error = None;
+                    }
+                    KeyCode::Backspace => {
+                        app_state.input.pop();
+                        app_state.input_error = None;
+                    }
+                    _ => {}
+                },
+                InputMode::WorkingDirectory => match key.code {
+                    KeyCode::Enter => {
+                        if let Some(selected_command) = app_state.selected_command {
+                            let category =
+                                &app_state.categories[app_state.selected_category.unwrap()];
+                            let (alias, entry) = &mut app_state.commands.get_mut(category).unwrap()
+                                [selected_command];
+                            entry.cwd = Some(app_state.input.clone()).filter(|cwd| !cwd.is_empty());
+                            update_command(category, entry, alias, &mut config, path);
+                            app_state.input_mode = InputMode::Normal;
+                            app_state.input.clear();
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app_state.input_mode = InputMode::Normal;
+                        app_state.input.clear();
+                    }
+                    KeyCode::Char(c) => {
+                        app_state.input.push(c);
+                    }
+                    KeyCode::Backspace => {
+                        app_state.input.pop();
+                    }
+                    _ => {}
+                },
+                InputMode::Adding => match key.code {
+                    KeyCode::Enter => {
+                        if app_state.mode == Mode::Category && !app_state.input.is_empty() {
+                            let category = app_state.input.clone();
+                            if is_reserved(&category) && app_state.input_error.is_none() {
+                                app_state.input_error = Some(RESERVED_WARNING.to_string());
+                            } else if !app_state.categories.contains(&category) {
+                                add_category_to_config(&category, &mut config, path);
+                                app_state.categories.push(category.clone());
+                                app_state.commands.insert(category, Vec::new());
+                                update_config_file(&config, path);
+                                app_state.input_mode = InputMode::Normal;
+                                app_state.input.clear();
+                                app_state.input_error = None;
+                            }
+                        } else if app_state.mode == Mode::Command && !app_state.input.is_empty() {
+                            let parts: Vec<&str> = app_state.input.splitn(2, ' ').collect();
+                            if parts.len() == 2 {
+                                let alias = parts[0].to_string();
+                                let command = CommandEntry::new(parts[1]);
+
+                                let category_index = app_state.selected_category.unwrap();
+                                let category = &app_state.categories[category_index];
+
+                                let alias_exists = config
+                                    .categories
+                                    .get(category)
+                                    .is_some_and(|cmds| cmds.contains_key(&alias));
+                                let category_exists = config.categories.contains_key(category);
+
+                                if !alias_exists {
+                                    if let Some(commands) = config.categories.get_mut(category) {
+                                        commands.insert(alias.clone(), command.clone());
+                                    } else {
+                                        let mut new_commands = IndexMap::new();
+                                        if !category_exists {
+                                            new_commands.insert(alias.clone(), command.clone());
+                                            config
+                                                .categories
+                                                .insert(category.clone(), new_commands);
+                                        }
+                                    }
+
+                                    app_state
+                                        .commands
+                                        .entry(category.clone())
+                                        .or_insert_with(Vec::new)
+                                        .push((alias.clone(), command.clone()));
+
+                                    update_config_file(&config, path);
+                                    app_state.mode = Mode::Command;
+                                    app_state.input_mode = InputMode::Normal;
+                                    app_state.input.clear();
+                                }
+                            }
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Up => {
+                        app_state.input_mode = InputMode::Normal;
+                        app_state.input.clear();
+                        app_state.input_error = None;
+                    }
+                    KeyCode::Char(c) => {
+                        app_state.input.push(c);
+                        app_state.input_error = None;
+                    }
+                    KeyCode::Backspace => {
+                        app_state.input.pop();
+                        app_state.input_error = None;
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    Ok(exit_code)
+}
+
+fn start_placeholder_input(app_state: &mut AppState) {
+    let placeholder = &mut app_state.placeholders[app_state.placeholder_values.len()];
+
+    // Without generated choices the value is typed instead
+    let _ = generate_choices(
+        placeholder,
+        app_state.pending_cwd.as_deref(),
+        &app_state.pending_command.env,
+    );
+
+    let initial = env_value(placeholder).or_else(|| placeholder.default.clone());
+    app_state.selected_choice = placeholder
+        .choices
+        .iter()
+        .position(|choice| Some(choice) == initial.as_ref())
+        .unwrap_or(0);
+    app_state.input = initial.unwrap_or_default();
+}
+
+fn load_lists(app_state: &mut AppState, config: &Config) {
+    app_state.categories = sorted_categories(config, config.sort)
+        .into_iter()
+        .cloned()
+        .collect();
+    app_state.commands = config
+        .categories
+        .iter()
+        .map(|(category, commands)| {
+            let cmd_list: Vec<(String, CommandEntry)> = sorted_commands(commands, config.sort)
+                .into_iter()
+                .map(|(alias, entry)| (alias.clone(), entry.clone()))
+                .collect();
+            (category.clone(), cmd_list)
+        })
+        .collect();
+}
+
+// Moving an entry stores the order that is shown, so a sorted list becomes the manual order
+fn store_shown_order(app_state: &AppState, config: &mut Config) {
+    config.sort = SortMode::Manual;
+    config.categories.sort_by_cached_key(|category, _| {
+        app_state
+            .categories
+            .iter()
+            .position(|shown| shown == category)
+    });
+    for (category, commands) in config.categories.iter_mut() {
+        if let Some(shown) = app_state.commands.get(category) {
+            commands
+                .sort_by_cached_key(|alias, _| shown.iter().position(|(shown, _)| shown == alias));
+        }
+    }
+}
+
+// Shift+Up/Down moves the selected category or command
+fn move_selected(app_state: &mut AppState, config: &mut Config, path: &Path, up: bool) {
+    let (list_len, selected) = match app_state.mode {
+        Mode::Category => (app_state.categories.len(), app_state.selected_category),
+        _ => {
+            let category = &app_state.categories[app_state.selected_category.unwrap()];
+            (
+                app_state.commands[category].len(),
+                app_state.selected_command,
+            )
+        }
+    };
+    let Some(selected) = selected else {
+        return;
+    };
+    let target = if up {
+        selected.checked_sub(1)
+    } else {
+        Some(selected + 1).filter(|target| *target < list_len)
+    };
+    let Some(target) = target else {
+        return;
+    };
+
+    if app_state.mode == Mode::Category {
+        app_state.categories.swap(selected, target);
+        app_state.selected_category = Some(target);
+    } else {
+        let category = &app_state.categories[app_state.selected_category.unwrap()];
+        app_state
+            .commands
+            .get_mut(category)
+            .unwrap()
+            .swap(selected, target);
+        app_state.selected_command = Some(target);
+    }
+    store_shown_order(app_state, config);
+    update_config_file(config, path);
+}
+
+// Switches to the next sort mode and keeps the selected category and command selected
+fn cycle_sort_mode(app_state: &mut AppState, config: &mut Config, path: &Path) {
+    let category = app_state
+        .selected_category
+        .map(|index| app_state.categories[index].clone());
+    let alias = category.as_ref().and_then(|category| {
+        let index = app_state.selected_command?;
+        Some(app_state.commands[category].get(index)?.0.clone())
+    });
+
+    config.sort = config.sort.next();
+    update_config_file(config, path);
+    load_lists(app_state, config);
+
+    if let Some(category) = category {
+        app_state.selected_category = app_state.categories.iter().position(|c| *c == category);
+        if let Some(alias) = alias {
+            app_state.selected_command = app_state.commands[&category]
+                .iter()
+                .position(|(a, _)| *a == alias);
+        }
+    }
+}
+
+fn add_command(
+    category: &str,
+    entry: &CommandEntry,
+    alias: &str,
+    config: &mut Config,
+    path: &Path,
+) {
+    if !check_if_category_exists(category, config) {
+        println!("Adding Category '{}', because it does not exist", category);
+        add_category_to_config(category, config, path);
+    }
+    if check_if_command_exists(category, alias, config) {
+        println!("Command '{}' already exists in category '{}', if you want to update the command, use update", entry.command, category);
     } else {
-        println!("Adding command '{}' to category '{}'", command, category);
-        add_command_to_config(category, command, alias, config, path);
+        println!(
+            "Adding command '{}' to category '{}'",
+            entry.command, category
+        );
+        add_command_to_config(category, entry, alias, config, path);
     }
 }
 
-fn run_command(category: &str, alias: &str, config: &Config) {
+fn update_command(
+    category: &str,
+    entry: &CommandEntry,
+    alias: &str,
+    config: &mut Config,
+    path: &Path,
+) {
     if !check_if_category_exists(category, config) {
-        println!("Category '{}' does not exist", category);
-    } else if !check_if_command_exists(category, alias, config) {
+        println!("Adding Category '{}', because it does not exist", category);
+        add_category_to_config(category, config, path);
+    }
+    if check_if_command_exists(category, alias, config) {
+        update_command_in_config(category, entry, alias, config, path);
+    } else {
         println!(
+            "Adding command '{}' to category '{}'",
+            entry.command, category
+        );
+        add_command_to_config(category, entry, alias, config, path);
+    }
+}
+
+// The use is recorded once the command is about to start, not for dry runs or runs that
+// are aborted while resolving it
+fn run_command(
+    category: &str,
+    alias: &str,
+    config: &mut Config,
+    path: &Path,
+    options: &RunOptions,
+) -> i32 {
+    if !check_if_category_exists(category, config) {
+        eprintln!("Category '{}' does not exist", category);
+        EXIT_CATEGORY_NOT_FOUND
+    } else if !check_if_command_exists(category, alias, config) {
+        eprintln!(
             "Command '{}' does not exist in category '{}'",
             alias, category
         );
+        EXIT_ALIAS_NOT_FOUND
+    } else {
+        run_command_from_config(category, alias, config, path, options)
+    }
+}
+
+// Names that are not found are taken as prefixes, so `bsh ut pi` runs `utilities ping` when
+// nothing else matches. Several matches are picked from, no match suggests similar names
+fn resolve_names(
+    category: &str,
+    alias: &str,
+    config: &Config,
+    options: &RunOptions,
+) -> Result<(String, String), i32> {
+    let categories: Vec<&String> = match config.categories.get_key_value(category) {
+        Some((category, _)) => vec![category],
+        None => config
+            .categories
+            .keys()
+            .filter(|name| name.starts_with(category))
+            .collect(),
+    };
+    if categories.is_empty() {
+        eprintln!("Category '{}' does not exist", category);
+        suggest_similar(category, config.categories.keys());
+        return Err(EXIT_CATEGORY_NOT_FOUND);
+    }
+
+    let exact: Vec<(&String, &String)> = categories
+        .iter()
+        .filter_map(|name| {
+            let (alias, _) = config.categories[*name].get_key_value(alias)?;
+            Some((*name, alias))
+        })
+        .collect();
+    let matches = if exact.is_empty() {
+        categories
+            .iter()
+            .flat_map(|name| {
+                config.categories[*name]
+                    .keys()
+                    .filter(|candidate| candidate.starts_with(alias))
+                    .map(move |candidate| (*name, candidate))
+            })
+            .collect()
+    } else {
+        exact
+    };
+
+    let names: Vec<String> = matches
+        .iter()
+        .map(|(category, alias)| format!("{} {}", category, alias))
+        .collect();
+    let picked = match matches.len() {
+        0 => {
+            if let [category] = categories[..] {
+                eprintln!(
+                    "Command '{}' does not exist in category '{}'",
+                    alias, category
+                );
+            } else {
+                eprintln!(
+                    "No command '{}' in the categories starting with '{}'",
+                    alias, category
+                );
+            }
+            suggest_similar(
+                alias,
+                categories
+                    .iter()
+                    .flat_map(|name| config.categories[*name].keys()),
+            );
+            return Err(EXIT_ALIAS_NOT_FOUND);
+        }
+        1 => 0,
+        _ if options.no_input || !io::stdin().is_terminal() => {
+            eprintln!(
+                "'{} {}' is ambiguous, it matches: {}",
+                category,
+                alias,
+                names.join(", ")
+            );
+            return Err(EXIT_FAILURE);
+        }
+        _ => match pick_from("Please select the command to run:", &names) {
+            Some(picked) => picked,
+            None => return Err(EXIT_FAILURE),
+        },
+    };
+
+    let (category_name, alias_name) = matches[picked];
+    if category_name != category || alias_name != alias {
+        eprintln!("Running '{}'", names[picked]);
+    }
+    Ok((category_name.clone(), alias_name.clone()))
+}
+
+fn suggest_similar<'a>(name: &str, candidates: impl Iterator<Item = &'a String>) {
+    let max_distance = 1 + name.chars().count() / 3;
+    let mut similar: Vec<(usize, &String)> = candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    similar.sort();
+    similar.dedup();
+
+    let similar: Vec<&str> = similar
+        .iter()
+        .take(3)
+        .map(|(_, candidate)| candidate.as_str())
+        .collect();
+    if !similar.is_empty() {
+        eprintln!("Did you mean: {}?", similar.join(", "));
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn check_for_config_file_or_create() -> PathBuf {
+    let expanded_path = expand_home_dir(CONFIG_FILE_PATH).expect("Failed to expand home directory");
+
+    if !config_file_exists(&expanded_path) {
+        create_config_file(&expanded_path);
+    }
+
+    expanded_path
+}
+
+fn config_file_exists(path: &Path) -> bool {
+    path.exists()
+}
+
+fn expand_home_dir(path: &str) -> Option<PathBuf> {
+    if path.starts_with('~') {
+        let home = home_dir()?;
+        let remaining_path = path.strip_prefix("~").unwrap_or(path);
+        let trimmed_path = remaining_path.trim_start_matches('/');
+        Some(home.join(trimmed_path))
     } else {
-        run_command_from_config(category, alias, config);
+        Some(PathBuf::from(path))
     }
 }
 
-fn check_for_config_file_or_create() -> PathBuf {
-    let expanded_path = expand_home_dir(CONFIG_FILE_PATH).expect("Failed to expand home directory");
+fn create_config_file(file_path: &Path) {
+    if let Some(parent) = file_path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            panic!("Failed to create configuration directory: {}", e);
+        }
+    }
+
+    fs::write(file_path, "{\"categories\":{}}").expect("Failed to create config file");
+}
+
+fn check_if_category_exists(category: &str, config: &Config) -> bool {
+    config.categories.contains_key(category)
+}
+
+fn check_if_command_exists(category: &str, alias: &str, config: &Config) -> bool {
+    config.categories.get(category).unwrap().contains_key(alias)
+}
+
+fn add_command_to_config(
+    category: &str,
+    entry: &CommandEntry,
+    alias: &str,
+    config: &mut Config,
+    path: &Path,
+) {
+    if !config.categories.contains_key(category) {
+        config
+            .categories
+            .insert(category.to_string(), IndexMap::new());
+    }
+    config
+        .categories
+        .get_mut(category)
+        .unwrap()
+        .insert(alias.to_string(), entry.clone());
+    update_config_file(config, path);
+}
+
+fn update_command_in_config(
+    category: &str,
+    entry: &CommandEntry,
+    alias: &str,
+    config: &mut Config,
+    path: &Path,
+) {
+    let mut entry = entry.clone();
+    entry.updated = Some(now());
+    config
+        .categories
+        .get_mut(category)
+        .unwrap()
+        .insert(alias.to_string(), entry);
+    update_config_file(config, path);
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn run_command_from_config(
+    category: &str,
+    alias: &str,
+    config: &mut Config,
+    path: &Path,
+    options: &RunOptions,
+) -> i32 {
+    let entry = match config.categories.get(category).and_then(|c| c.get(alias)) {
+        Some(entry) => with_category_settings(category, entry, config),
+        None => {
+            eprintln!(
+                "Command for category '{}' and alias '{}' not found.",
+                category, alias
+            );
+            return EXIT_ALIAS_NOT_FOUND;
+        }
+    };
+
+    if entry.command.trim().is_empty() {
+        eprintln!("Command '{}' is empty", entry.command);
+        return EXIT_FAILURE;
+    }
+
+    // Generated choices are collected in the directory the command runs in
+    let cwd = match entry.cwd.as_deref().map(resolve_working_directory) {
+        Some(Ok(cwd)) => Some(cwd),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return EXIT_FAILURE;
+        }
+        None => None,
+    };
+
+    let mut placeholders = match find_entry_placeholders(&entry) {
+        Ok(placeholders) => placeholders,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILURE;
+        }
+    };
+
+    let mut positional_values = options.values.iter();
+    let mut values = Vec::new();
+    for placeholder in &mut placeholders {
+        let given = match options
+            .vars
+            .get(&placeholder.name)
+            .or_else(|| positional_values.next())
+        {
+            Some(value) => Some(value.clone()),
+            None => env_value(placeholder).inspect(|value| {
+                if options.show_env {
+                    eprintln!(
+                        "Using {} for {}: {}",
+                        env_var_name(&placeholder.name),
+                        placeholder.name,
+                        if placeholder.secret { "****" } else { value }
+                    );
+                }
+            }),
+        };
+
+        let value = match given {
+            Some(value) => {
+                if !placeholder.choices.is_empty() && !placeholder.choices.contains(&value) {
+                    eprintln!(
+                        "'{}' is not a valid value for {}, expected one of: {}",
+                        value,
+                        placeholder.name,
+                        placeholder.choices.join(", ")
+                    );
+                    return EXIT_PLACEHOLDER_ABORTED;
+                }
+                value
+            }
+            None if options.no_input => match &placeholder.default {
+                Some(default) => default.clone(),
+                None => {
+                    eprintln!(
+                        "No value given for {}, use --var {}=VALUE or set {}",
+                        placeholder.name,
+                        placeholder.name,
+                        env_var_name(&placeholder.name)
+                    );
+                    return EXIT_PLACEHOLDER_ABORTED;
+                }
+            },
+            None => {
+                if let Err(e) = generate_choices(placeholder, cwd.as_deref(), &entry.env) {
+                    eprintln!("{}, please enter the value instead", e);
+                }
+                match prompt_for_placeholder(placeholder) {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("\nNo value given for {}, aborting", placeholder.name);
+                        return EXIT_PLACEHOLDER_ABORTED;
+                    }
+                }
+            }
+        };
+        values.push(value);
+    }
+
+    // Values left after filling the placeholders are passed on like the ones after `--`
+    let args: Vec<String> = positional_values
+        .chain(options.args.iter())
+        .cloned()
+        .collect();
+
+    run_resolved_command(
+        &entry,
+        cwd.as_deref(),
+        &placeholders,
+        &values,
+        &args,
+        options.dry_run,
+        || record_use(category, alias, config, path),
+    )
+}
+
+// The settings of the category apply to all its commands, their own values take precedence
+fn with_category_settings(category: &str, entry: &CommandEntry, config: &Config) -> CommandEntry {
+    let mut entry = entry.clone();
+    if let Some(settings) = config.category_settings.get(category) {
+        for (name, value) in &settings.env {
+            entry
+                .env
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        entry.shell = entry.shell.or_else(|| settings.shell.clone());
+    }
+    entry.shell = entry.shell.or_else(|| config.shell.clone());
+    entry
+}
+
+fn interpreter(shell: Option<&str>) -> Interpreter {
+    let mut argv: Vec<String> = shell
+        .unwrap_or("sh")
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    if argv.is_empty() {
+        argv.push("sh".to_string());
+    }
+
+    let program = Path::new(&argv[0])
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let posix = matches!(program.as_str(), "sh" | "bash" | "zsh" | "dash" | "ksh");
+    let known = posix || matches!(program.as_str(), "fish" | "python" | "python3");
+
+    // Known shells and interpreters are given just by name, anything else is used as is
+    if known && argv.len() == 1 {
+        argv.push("-c".to_string());
+    }
+
+    Interpreter {
+        argv,
+        posix,
+        shell: posix || program == "fish",
+    }
+}
+
+// Placeholders that only appear in environment values are asked after the ones in the command
+fn find_entry_placeholders(entry: &CommandEntry) -> Result<Vec<Placeholder>, String> {
+    let mut placeholders = find_placeholders(&entry.command)?;
+    let mut names: Vec<&String> = entry.env.keys().collect();
+    names.sort();
+    for name in names {
+        for mut placeholder in find_placeholders(&entry.env[name])? {
+            if placeholders.iter().all(|p| p.name != placeholder.name) {
+                placeholder.occurrences.clear();
+                placeholders.push(placeholder);
+            }
+        }
+    }
+    Ok(placeholders)
+}
+
+// Environment values are not parsed by a shell, so placeholder values are inserted as they are
+fn substitute_env(
+    env: &HashMap<String, String>,
+    placeholders: &[Placeholder],
+    values: &[String],
+) -> Vec<(String, String)> {
+    let mut resolved: Vec<(String, String)> = env
+        .iter()
+        .map(|(name, value)| {
+            let mut found = find_placeholders(value).unwrap_or_default();
+            let found_values: Vec<String> = found
+                .iter_mut()
+                .map(|placeholder| {
+                    placeholder
+                        .occurrences
+                        .iter_mut()
+                        .for_each(|occurrence| occurrence.raw = true);
+                    placeholders
+                        .iter()
+                        .position(|p| p.name == placeholder.name)
+                        .and_then(|i| values.get(i))
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .collect();
+            (
+                name.clone(),
+                substitute_placeholders(value, &found, &found_values),
+            )
+        })
+        .collect();
+    resolved.sort();
+    resolved
+}
+
+fn run_resolved_command(
+    entry: &CommandEntry,
+    cwd: Option<&Path>,
+    placeholders: &[Placeholder],
+    values: &[String],
+    args: &[String],
+    dry_run: bool,
+    on_start: impl FnOnce(),
+) -> i32 {
+    let interpreter = interpreter(entry.shell.as_deref());
+    let mut placeholders = placeholders.to_vec();
+    if !interpreter.shell {
+        // Other interpreters would not understand shell quoting
+        for placeholder in &mut placeholders {
+            for occurrence in &mut placeholder.occurrences {
+                occurrence.raw = true;
+            }
+        }
+    }
+    let appended_args = if interpreter.shell { args } else { &[] };
+
+    if dry_run {
+        let masked_values = mask_secrets(&placeholders, values);
+        let final_command =
+            resolve_command(&entry.command, &placeholders, &masked_values, appended_args);
+        let env = substitute_env(&entry.env, &placeholders, &masked_values);
+        let refers_to_args = uses_positional_args(&entry.command);
+        println!(
+            "{}",
+            preview_command(
+                &final_command,
+                args,
+                refers_to_args,
+                cwd,
+                &env,
+                &interpreter
+            )
+        );
+        return 0;
+    }
+
+    let final_command = resolve_command(&entry.command, &placeholders, values, appended_args);
+    let env = substitute_env(&entry.env, &placeholders, values);
+    on_start();
+    execute_command(&final_command, args, cwd, &env, &interpreter)
+}
+
+// Relative directories start from the root of the git repository, or the current directory outside of one
+fn resolve_working_directory(cwd: &str) -> Result<PathBuf, String> {
+    let expanded = expand_home_dir(cwd).ok_or("Could not find the home directory")?;
+    let dir = if expanded.is_absolute() {
+        expanded
+    } else {
+        let toplevel = processCommand::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()));
+        match toplevel {
+            Some(root) => root.join(expanded),
+            None => env::current_dir()
+                .map_err(|e| format!("Failed to read the current directory: {}", e))?
+                .join(expanded),
+        }
+    };
+
+    if !dir.is_dir() {
+        return Err(format!(
+            "Working directory '{}' does not exist",
+            dir.display()
+        ));
+    }
+    Ok(dir)
+}
+
+fn resolve_command(
+    command: &str,
+    placeholders: &[Placeholder],
+    values: &[String],
+    args: &[String],
+) -> String {
+    let mut final_command = substitute_placeholders(command, placeholders, values);
+    if !args.is_empty() && !uses_positional_args(command) {
+        for arg in args {
+            final_command.push(' ');
+            final_command.push_str(&shell_quote(arg));
+        }
+    }
+    final_command
+}
+
+// Secret values are never shown, not even in a printed command
+fn mask_secrets(placeholders: &[Placeholder], values: &[String]) -> Vec<String> {
+    placeholders
+        .iter()
+        .zip(values)
+        .map(|(placeholder, value)| {
+            if placeholder.secret {
+                "****".to_string()
+            } else {
+                value.clone()
+            }
+        })
+        .collect()
+}
+
+// A command that refers to its arguments needs them set to be run on its own
+fn preview_command(
+    final_command: &str,
+    args: &[String],
+    refers_to_args: bool,
+    cwd: Option<&Path>,
+    env: &[(String, String)],
+    interpreter: &Interpreter,
+) -> String {
+    // Commands for other interpreters are printed as a complete invocation
+    let mut preview = if interpreter.posix {
+        final_command.to_string()
+    } else {
+        let quoted: Vec<String> = interpreter
+            .argv
+            .iter()
+            .map(String::as_str)
+            .chain([final_command])
+            .chain(args.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect();
+        quoted.join(" ")
+    };
+    if let Some(cwd) = cwd {
+        preview = format!("cd {} && {}", shell_quote(&cwd.to_string_lossy()), preview);
+    }
+    if !env.is_empty() {
+        let assignments: Vec<String> = env
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, shell_quote(value)))
+            .collect();
+        preview = format!("export {}; {}", assignments.join(" "), preview);
+    }
+    if !interpreter.posix || args.is_empty() || !refers_to_args {
+        return preview;
+    }
+
+    let quoted_args: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+    format!("set -- {}; {}", quoted_args.join(" "), preview)
+}
+
+// Looks at the stored command, not at placeholder values or single-quoted text like an
+// awk program, where `$1` does not refer to the arguments
+fn uses_positional_args(command: &str) -> bool {
+    let mut spans: Vec<(usize, usize)> = find_placeholders(command)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|placeholder| &placeholder.occurrences)
+        .map(|occurrence| (occurrence.start, occurrence.end))
+        .collect();
+    spans.sort();
+    spans.push((command.len(), command.len()));
+
+    let mut uses = false;
+    let mut quotes = Quotes::None;
+    let mut offset = 0;
+    for (start, end) in spans {
+        quotes = follow_quotes(&command[offset..start], quotes, |rest, quotes| {
+            let mut chars = rest.chars();
+            let parameter = match (chars.next(), chars.next()) {
+                (Some('$'), Some('{')) => chars.next(),
+                (Some('$'), next) => next,
+                _ => None,
+            };
+            if quotes != Quotes::Single && matches!(parameter, Some('@' | '*' | '1'..='9')) {
+                uses = true;
+            }
+        });
+        offset = end;
+    }
+    uses
+}
+
+fn find_placeholders(command: &str) -> Result<Vec<Placeholder>, String> {
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    let mut offset = 0;
+    let mut quotes = Quotes::None;
+
+    while let Some(found) = command[offset..].find("<[") {
+        let start = offset + found;
+        quotes = follow_quotes(&command[offset..start], quotes, |_, _| {});
+        let Some(length) = command[start..].find("]>") else {
+            return Err(format!(
+                "Mismatched placeholder brackets in command: {}",
+                command
+            ));
+        };
+        let end = start + length + 2;
+        let body = &command[start + 2..end - 2];
+        let mut raw = false;
+        let mut secret = false;
+        let body = body.trim_start_matches(|c| match c {
+            '!' => {
+                raw = true;
+                true
+            }
+            '*' => {
+                secret = true;
+                true
+            }
+            _ => false,
+        });
+        let occurrence = Occurrence {
+            start,
+            end,
+            raw,
+            quotes,
+        };
+
+        let mut default = None;
+        let mut choices = Vec::new();
+        let mut generator = None;
+        let name = match body.find([':', '|', '@']) {
+            Some(i) => {
+                let rest = &body[i + 1..];
+                match &body[i..i + 1] {
+                    ":" => default = Some(rest.to_string()),
+                    "|" => choices = rest.split(',').map(|c| c.trim().to_string()).collect(),
+                    _ => generator = Some(rest.to_string()),
+                }
+                &body[..i]
+            }
+            None => body,
+        };
+
+        // Repeated placeholders are asked once, the first occurrence defines the options
+        match placeholders.iter_mut().find(|p| p.name == name) {
+            Some(placeholder) => placeholder.occurrences.push(occurrence),
+            None => placeholders.push(Placeholder {
+                name: name.to_string(),
+                default,
+                choices,
+                generator,
+                secret,
+                occurrences: vec![occurrence],
+            }),
+        }
+        offset = end;
+    }
+
+    Ok(placeholders)
+}
+
+// Follows the quoting of shell text, returns the quotes still open at its end. `visit` gets
+// the rest of the text at every unescaped character and the quotes it is in
+fn follow_quotes(text: &str, mut quotes: Quotes, mut visit: impl FnMut(&str, Quotes)) -> Quotes {
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        visit(&text[i..], quotes);
+        quotes = match (quotes, c) {
+            (Quotes::Single, '\'') => Quotes::None,
+            (Quotes::Single, _) => Quotes::Single,
+            (_, '\\') => {
+                chars.next();
+                quotes
+            }
+            (Quotes::None, '\'') => Quotes::Single,
+            (Quotes::None, '"') => Quotes::Double,
+            (Quotes::Double, '"') => Quotes::None,
+            _ => quotes,
+        };
+    }
+    quotes
+}
+
+fn env_var_name(placeholder_name: &str) -> String {
+    let name: String = placeholder_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("BSH_VAR_{}", name)
+}
+
+fn env_value(placeholder: &Placeholder) -> Option<String> {
+    env::var(env_var_name(&placeholder.name)).ok()
+}
+
+// Generators run where the command would, with the environment values that do not wait
+// for a placeholder themselves
+fn generate_choices(
+    placeholder: &mut Placeholder,
+    cwd: Option<&Path>,
+    env: &HashMap<String, String>,
+) -> Result<(), String> {
+    let Some(generator) = &placeholder.generator else {
+        return Ok(());
+    };
+
+    let mut command = processCommand::new("sh");
+    command
+        .arg("-c")
+        .arg(generator)
+        .envs(env.iter().filter(|(_, value)| !value.contains("<[")))
+        .stdin(Stdio::null());
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run '{}': {}", generator, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("'{}' failed with {}", generator, output.status),
+            stderr => format!("'{}' failed: {}", generator, stderr),
+        });
+    }
+
+    let choices: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+
+    if choices.is_empty() {
+        return Err(format!("'{}' did not produce any choices", generator));
+    }
+
+    placeholder.choices = choices;
+    Ok(())
+}
+
+// Prompts go to stderr, so the output of `--dry-run` can be captured on its own
+fn prompt_for_placeholder(placeholder: &Placeholder) -> Option<String> {
+    if !placeholder.choices.is_empty() {
+        return prompt_for_choice(placeholder);
+    }
+
+    match &placeholder.default {
+        Some(_) if placeholder.secret => eprint!(
+            "Please enter a value for {} (hidden, Enter for default): ",
+            placeholder.name
+        ),
+        None if placeholder.secret => {
+            eprint!("Please enter a value for {} (hidden): ", placeholder.name)
+        }
+        Some(default) => eprint!(
+            "Please enter a value for {} [{}]: ",
+            placeholder.name, default
+        ),
+        None => eprint!("Please enter a value for {}: ", placeholder.name),
+    }
+    io::stderr().flush().unwrap();
+
+    let mut input = String::new();
+    let read = if placeholder.secret {
+        read_line_without_echo(&mut input)
+    } else {
+        io::stdin().read_line(&mut input)
+    };
+    match read {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            let input = input.trim();
+            match &placeholder.default {
+                Some(default) if input.is_empty() => Some(default.clone()),
+                _ => Some(input.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn read_line_without_echo(input: &mut String) -> io::Result<usize> {
+    use std::io::Read;
+
+    let fd = libc::STDIN_FILENO;
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+
+    // Not a terminal (e.g. piped input), there is no echo to turn off
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return io::stdin().read_line(input);
+    }
+
+    // Ctrl-C must not kill bsh while echo is off, it ends the line instead and is reported
+    // as an interruption once the terminal is restored
+    let interrupt = original.c_cc[libc::VINTR];
+    let mut silent = original;
+    silent.c_lflag &= !(libc::ECHO | libc::ISIG);
+    silent.c_cc[libc::VEOL] = interrupt;
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) };
+
+    // Each read returns at most one line, which ends with a newline or the interrupt
+    let mut line = Vec::new();
+    let mut buffer = [0; 1024];
+    let read = loop {
+        match io::stdin().read(&mut buffer) {
+            Ok(0) => break Ok(line.len()),
+            Ok(n) => {
+                line.extend_from_slice(&buffer[..n]);
+                if matches!(line.last(), Some(&last) if last == b'\n' || last == interrupt) {
+                    break Ok(line.len());
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => break Err(e),
+        }
+    };
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    eprintln!();
+
+    if interrupt != 0 && line.last() == Some(&interrupt) {
+        return Err(io::ErrorKind::Interrupted.into());
+    }
+    input.push_str(&String::from_utf8_lossy(&line));
+    read
+}
+
+#[cfg(not(unix))]
+fn read_line_without_echo(input: &mut String) -> io::Result<usize> {
+    io::stdin().read_line(input)
+}
+
+fn prompt_for_choice(placeholder: &Placeholder) -> Option<String> {
+    let prompt = format!("Please select a value for {}:", placeholder.name);
+    pick_from(&prompt, &placeholder.choices).map(|i| placeholder.choices[i].clone())
+}
+
+// Returns the index of the choice picked by number or by value
+fn pick_from(prompt: &str, choices: &[String]) -> Option<usize> {
+    eprintln!("{}", prompt);
+    for (i, choice) in choices.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, choice);
+    }
+
+    loop {
+        eprint!("Choice [1-{}]: ", choices.len());
+        io::stderr().flush().unwrap();
+
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => {}
+        }
+        let input = input.trim();
+
+        let by_number = input
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .filter(|i| *i < choices.len());
+        let by_value = choices.iter().position(|c| c.as_str() == input);
+
+        match by_number.or(by_value) {
+            Some(i) => return Some(i),
+            None => eprintln!("'{}' is not one of: {}", input, choices.join(", ")),
+        }
+    }
+}
+
+fn substitute_placeholders(
+    command: &str,
+    placeholders: &[Placeholder],
+    values: &[String],
+) -> String {
+    let mut replacements: Vec<(&Occurrence, &String)> = placeholders
+        .iter()
+        .zip(values)
+        .flat_map(|(placeholder, value)| {
+            placeholder
+                .occurrences
+                .iter()
+                .map(move |occurrence| (occurrence, value))
+        })
+        .collect();
+    replacements.sort_by_key(|(occurrence, _)| occurrence.start);
+
+    let mut final_command = String::new();
+    let mut offset = 0;
+
+    for (occurrence, value) in replacements {
+        final_command.push_str(&command[offset..occurrence.start]);
+        if occurrence.raw {
+            final_command.push_str(value);
+        } else {
+            final_command.push_str(&quote_for(value, occurrence.quotes));
+        }
+        offset = occurrence.end;
+    }
+    final_command.push_str(&command[offset..]);
+
+    final_command
+}
+
+// Inside quotes the value only needs the characters escaped that would end them or, in
+// double quotes, start an expansion
+fn quote_for(value: &str, quotes: Quotes) -> String {
+    match quotes {
+        Quotes::None => shell_quote(value),
+        Quotes::Single => value.replace('\'', "'\\''"),
+        Quotes::Double => {
+            let mut escaped = String::new();
+            for c in value.chars() {
+                if matches!(c, '$' | '`' | '"' | '\\') {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
+        }
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@,+%".contains(c);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        return value.to_string();
+    }
+
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn execute_command(
+    final_command: &str,
+    args: &[String],
+    cwd: Option<&Path>,
+    env: &[(String, String)],
+    interpreter: &Interpreter,
+) -> i32 {
+    let mut command = processCommand::new(&interpreter.argv[0]);
+    command.args(&interpreter.argv[1..]).arg(final_command);
+    if interpreter.posix {
+        command.arg("bsh");
+    }
+    command
+        .args(args)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    unblock_signals_in_child(&mut command);
+
+    let signals = forward_signals();
+    let exit_code = match command.spawn() {
+        Ok(mut child) => {
+            set_signal_target(&signals, &child);
+            match child.wait() {
+                Ok(status) => exit_code_from_status(status),
+                Err(e) => {
+                    eprintln!("Failed to wait for command: {}", e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "Failed to execute command with '{}': {}",
+                interpreter.argv[0], e
+            );
+            EXIT_SPAWN_FAILED
+        }
+    };
+    stop_forwarding_signals(signals);
+
+    exit_code
+}
+
+// The handlers replaced while a command runs, and the signals they handle
+#[cfg(unix)]
+struct ForwardedSignals {
+    previous: Vec<(libc::c_int, libc::sigaction)>,
+    mask: libc::sigset_t,
+}
+
+#[cfg(not(unix))]
+struct ForwardedSignals;
 
-    if !config_file_exists(&expanded_path) {
-        create_config_file(&expanded_path);
-    }
+// SIGINT and SIGTERM stay blocked until the child is spawned, so none arrive before
+// there is a pid to forward them to
+#[cfg(unix)]
+fn forward_signals() -> ForwardedSignals {
+    let signals = [libc::SIGINT, libc::SIGTERM];
+    unsafe {
+        let mut mask: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut mask);
+        for signal in signals {
+            libc::sigaddset(&mut mask, signal);
+        }
+        libc::pthread_sigmask(libc::SIG_BLOCK, &mask, std::ptr::null_mut());
 
-    expanded_path
+        let previous = signals
+            .into_iter()
+            .map(|signal| {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = forward_signal as *const () as libc::sighandler_t;
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut previous: libc::sigaction = std::mem::zeroed();
+                libc::sigaction(signal, &action, &mut previous);
+                (signal, previous)
+            })
+            .collect();
+
+        ForwardedSignals { previous, mask }
+    }
 }
 
-fn config_file_exists(path: &Path) -> bool {
-    path.exists()
+#[cfg(not(unix))]
+fn forward_signals() -> ForwardedSignals {
+    ForwardedSignals
 }
 
-fn expand_home_dir(path: &str) -> Option<PathBuf> {
-    if path.starts_with('~') {
-        let home = home_dir()?;
-        let remaining_path = path.strip_prefix("~").unwrap_or(path);
-        let trimmed_path = remaining_path.trim_start_matches('/');
-        Some(home.join(trimmed_path))
-    } else {
-        Some(PathBuf::from(path))
+#[cfg(unix)]
+fn set_signal_target(signals: &ForwardedSignals, child: &Child) {
+    CHILD_PID.store(child.id() as i32, Ordering::SeqCst);
+    unsafe {
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &signals.mask, std::ptr::null_mut());
     }
 }
 
-fn create_config_file(file_path: &Path) {
-    if let Some(parent) = file_path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            panic!("Failed to create configuration directory: {}", e);
-        }
-    }
+// The child inherits the signals blocked by `forward_signals`, it must not keep them blocked
+#[cfg(unix)]
+fn unblock_signals_in_child(command: &mut processCommand) {
+    use std::os::unix::process::CommandExt;
 
-    fs::write(file_path, "{\"categories\":{}}").expect("Failed to create config file");
+    unsafe {
+        command.pre_exec(|| {
+            let mut mask: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut mask);
+            libc::pthread_sigmask(libc::SIG_SETMASK, &mask, std::ptr::null_mut());
+            Ok(())
+        });
+    }
 }
 
-fn check_if_category_exists(category: &str, config: &Config) -> bool {
-    config.categories.contains_key(category)
-}
+#[cfg(not(unix))]
+fn unblock_signals_in_child(_command: &mut processCommand) {}
 
-fn check_if_command_exists(category: &str, alias: &str, config: &Config) -> bool {
-    config.categories.get(category).unwrap().contains_key(alias)
-}
+#[cfg(not(unix))]
+fn set_signal_target(_signals: &ForwardedSignals, _child: &Child) {}
 
-fn add_command_to_config(
-    category: &str,
-    command: &str,
-    alias: &str,
-    config: &mut Config,
-    path: &Path,
-) {
-    if !config.categories.contains_key(category) {
-        config
-            .categories
-            .insert(category.to_string(), HashMap::new());
+// Once the child has exited the previous handlers apply again, signals that were blocked
+// because spawning failed are delivered to them
+#[cfg(unix)]
+fn stop_forwarding_signals(signals: ForwardedSignals) {
+    CHILD_PID.store(0, Ordering::SeqCst);
+    unsafe {
+        for (signal, previous) in &signals.previous {
+            libc::sigaction(*signal, previous, std::ptr::null_mut());
+        }
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &signals.mask, std::ptr::null_mut());
     }
-    config
-        .categories
-        .get_mut(category)
-        .unwrap()
-        .insert(alias.to_string(), command.to_string());
-    update_config_file(config, path);
 }
 
-fn update_command_in_config(
-    category: &str,
-    command: &str,
-    alias: &str,
-    config: &mut Config,
-    path: &Path,
-) {
-    config
-        .categories
-        .get_mut(category)
-        .unwrap()
-        .insert(alias.to_string(), command.to_string());
-    update_config_file(config, path);
-}
+#[cfg(not(unix))]
+fn stop_forwarding_signals(_signals: ForwardedSignals) {}
 
-fn run_command_from_config(category: &str, alias: &str, config: &Config) {
-    let command_to_run = match config.categories.get(category).and_then(|c| c.get(alias)) {
-        Some(cmd) => cmd,
-        None => {
-            eprintln!(
-                "Command for category '{}' and alias '{}' not found.",
-                category, alias
-            );
-            return;
+#[cfg(unix)]
+extern "C" fn forward_signal(
+    signal: libc::c_int,
+    info: *mut libc::siginfo_t,
+    _context: *mut libc::c_void,
+) {
+    // Signals generated by the terminal (e.g. Ctrl-C) already reach the child through
+    // the foreground process group, only forward the ones sent to bsh by another process
+    let sent_by_process = unsafe { (*info).si_code } <= 0;
+    let pid = CHILD_PID.load(Ordering::SeqCst);
+    if pid <= 0 {
+        // The command has already exited, handle the signal the default way
+        unsafe {
+            libc::signal(signal, libc::SIG_DFL);
+            libc::raise(signal);
         }
-    };
+    } else if sent_by_process {
+        unsafe {
+            libc::kill(pid, signal);
+        }
+    }
+}
 
-    if command_to_run.trim().is_empty() {
-        eprintln!("Command '{}' is empty", command_to_run);
-        return;
+fn exit_code_from_status(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
     }
 
-    let mut final_command = command_to_run.clone();
-    while let Some(start) = final_command.find("<[") {
-        if let Some(end) = final_command[start..].find("]>") {
-            let placeholder = &final_command[start + 2..start + end];
-            print!("Please enter a value for {}: ", placeholder);
-            io::stdout().flush().unwrap();
-            let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read input");
-            let input = input.trim();
-            final_command = final_command.replacen(&format!("<[{}]>", placeholder), input, 1);
-        } else {
-            eprintln!(
-                "Mismatched placeholder brackets in command: {}",
-                final_command
-            );
-            return;
+    // A child killed by a signal has no exit code, report it the way shells do
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
         }
     }
 
-    let output = processCommand::new("sh")
-        .arg("-c")
-        .arg(final_command)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .output();
-
-    if let Err(e) = output {
-        eprintln!("Failed to execute command: {}", e);
-    }
+    EXIT_FAILURE
 }
 
 fn remove_command_from_config(category: &str, alias: &str, config: &mut Config, path: &Path) {
     if let Some(commands) = config.categories.get_mut(category) {
-        if commands.remove(alias).is_some() {
+        if commands.shift_remove(alias).is_some() {
             update_config_file(config, path);
         }
     }
@@ -1157,37 +3782,158 @@ fn add_category_to_config(category: &str, config: &mut Config, path: &Path) {
     if !config.categories.contains_key(category) {
         config
             .categories
-            .insert(category.to_string(), HashMap::new());
+            .insert(category.to_string(), IndexMap::new());
         update_config_file(config, path);
     }
 }
 
 fn remove_category_from_config(category: &str, config: &mut Config, path: &Path) {
-    if config.categories.remove(category).is_some() {
+    config.category_settings.remove(category);
+    if config.categories.shift_remove(category).is_some() {
         update_config_file(config, path);
     }
 }
 
-fn list_all_commands_with_aliases(config: &Config) {
+// Moved commands keep their metadata, copies start with their own timestamps and usage.
+// Returns the alias the command ended up with in the destination
+fn transfer_command(
+    category: &str,
+    alias: &str,
+    target_category: &str,
+    target_alias: &str,
+    on_conflict: OnConflict,
+    copy: bool,
+    config: &mut Config,
+) -> Result<String, String> {
+    let Some(entry) = config
+        .categories
+        .get(category)
+        .and_then(|commands| commands.get(alias))
+    else {
+        return Err(format!(
+            "Command '{}' does not exist in category '{}'",
+            alias, category
+        ));
+    };
+    // Only a copy with a suffix can end up next to its source
+    let same = category == target_category && alias == target_alias;
+    if same && !(copy && matches!(on_conflict, OnConflict::Suffix)) {
+        return Err(format!(
+            "'{}/{}' is both the source and the destination",
+            category, alias
+        ));
+    }
+
+    let mut entry = entry.clone();
+    if copy {
+        entry.created = Some(now());
+        entry.updated = None;
+        entry.uses = 0;
+        entry.last_used = None;
+    }
+
+    let taken = |alias: &str| {
+        config
+            .categories
+            .get(target_category)
+            .is_some_and(|commands| commands.contains_key(alias))
+    };
+    let target_alias = match on_conflict {
+        _ if !taken(target_alias) => target_alias.to_string(),
+        OnConflict::Fail => {
+            return Err(format!(
+                "Command '{}' already exists in category '{}'",
+                target_alias, target_category
+            ))
+        }
+        OnConflict::Overwrite => target_alias.to_string(),
+        OnConflict::Suffix => (2..)
+            .map(|n| format!("{}-{}", target_alias, n))
+            .find(|alias| !taken(alias))
+            .unwrap(),
+    };
+
+    if !copy {
+        config
+            .categories
+            .get_mut(category)
+            .unwrap()
+            .shift_remove(alias);
+    }
+    config
+        .categories
+        .entry(target_category.to_string())
+        .or_default()
+        .insert(target_alias.clone(), entry);
+    Ok(target_alias)
+}
+
+// Renamed categories and commands keep their position and metadata
+fn rename_category(category: &str, new_name: &str, config: &mut Config) -> Result<(), String> {
+    if config.categories.contains_key(new_name) {
+        return Err(format!("Category '{}' already exists", new_name));
+    }
+    let Some((index, _, commands)) = config.categories.shift_remove_full(category) else {
+        return Err(format!("Category '{}' does not exist", category));
+    };
+    config
+        .categories
+        .shift_insert(index, new_name.to_string(), commands);
+    if let Some(settings) = config.category_settings.remove(category) {
+        config
+            .category_settings
+            .insert(new_name.to_string(), settings);
+    }
+    Ok(())
+}
+
+fn rename_command(
+    category: &str,
+    alias: &str,
+    new_alias: &str,
+    config: &mut Config,
+) -> Result<(), String> {
+    let Some(commands) = config.categories.get_mut(category) else {
+        return Err(format!("Category '{}' does not exist", category));
+    };
+    if commands.contains_key(new_alias) {
+        return Err(format!(
+            "Command '{}' already exists in category '{}'",
+            new_alias, category
+        ));
+    }
+    let Some((index, _, entry)) = commands.shift_remove_full(alias) else {
+        return Err(format!(
+            "Command '{}' does not exist in category '{}'",
+            alias, category
+        ));
+    };
+    commands.shift_insert(index, new_alias.to_string(), entry);
+    Ok(())
+}
+
+fn list_all_commands_with_aliases(config: &Config, sort: SortMode) {
     if config.categories.is_empty() {
         println!("{}", "No categories available.".yellow().bold());
     } else {
-        for (category, commands) in config.categories.iter() {
+        for category in sorted_categories(config, sort) {
+            let commands = &config.categories[category];
             println!(
                 "{}{}{}",
                 "Commands in category ".blue().bold(),
                 "➜  ".yellow().bold(),
                 category.red().bold()
             );
+            print_category_settings(category, config);
             if commands.is_empty() {
                 println!("\t{}", "No commands available.".yellow());
             } else {
-                for (alias, command) in commands.iter() {
+                for (alias, entry) in sorted_commands(commands, sort) {
                     println!(
                         "\t {} {}  {}",
                         alias.green().bold(),
                         "➜".yellow().bold(),
-                        command
+                        format_entry(entry)
                     );
                 }
             }
@@ -1195,7 +3941,51 @@ fn list_all_commands_with_aliases(config: &Config) {
     }
 }
 
-fn list_all_commands_with_aliases_in_category(category: &str, config: &Config) {
+fn format_entry(entry: &CommandEntry) -> String {
+    let mut formatted = entry.command.clone();
+    if let Some(cwd) = &entry.cwd {
+        formatted.push_str(&format!("  {}", format!("(in {})", cwd).dimmed()));
+    }
+    if !entry.env.is_empty() {
+        formatted.push_str(&format!(
+            "  {}",
+            format!("(env {})", env_names(&entry.env)).dimmed()
+        ));
+    }
+    if let Some(shell) = &entry.shell {
+        formatted.push_str(&format!("  {}", format!("(shell {})", shell).dimmed()));
+    }
+    for tag in &entry.tags {
+        formatted.push_str(&format!("  {}", format!("#{}", tag).cyan()));
+    }
+    if let Some(description) = &entry.description {
+        formatted.push_str(&format!("\n\t     {}", description.italic()));
+    }
+    formatted
+}
+
+fn print_category_settings(category: &str, config: &Config) {
+    if let Some(settings) = config.category_settings.get(category) {
+        if !settings.env.is_empty() {
+            println!(
+                "\t{}",
+                format!("(env {})", env_names(&settings.env)).dimmed()
+            );
+        }
+        if let Some(shell) = &settings.shell {
+            println!("\t{}", format!("(shell {})", shell).dimmed());
+        }
+    }
+}
+
+// Only the names are shown, values may be secrets
+fn env_names(env: &HashMap<String, String>) -> String {
+    let mut names: Vec<&str> = env.keys().map(String::as_str).collect();
+    names.sort();
+    names.join(", ")
+}
+
+fn list_all_commands_with_aliases_in_category(category: &str, config: &Config, sort: SortMode) {
     if let Some(commands) = config.categories.get(category) {
         println!(
             "{}{}{}",
@@ -1203,15 +3993,16 @@ fn list_all_commands_with_aliases_in_category(category: &str, config: &Config) {
             "➜  ".yellow().bold(),
             category.red().bold()
         );
+        print_category_settings(category, config);
         if commands.is_empty() {
             println!("\t{}", "No commands available in this category.".yellow());
         } else {
-            for (alias, command) in commands.iter() {
+            for (alias, entry) in sorted_commands(commands, sort) {
                 println!(
                     "\t {} {}  {}",
                     alias.green().bold(),
                     "➜".yellow().bold(),
-                    command
+                    format_entry(entry)
                 );
             }
         }
@@ -1224,7 +4015,454 @@ fn list_all_commands_with_aliases_in_category(category: &str, config: &Config) {
     }
 }
 
+fn sorted_categories(config: &Config, sort: SortMode) -> Vec<&String> {
+    let mut categories: Vec<&String> = config.categories.keys().collect();
+    match sort {
+        SortMode::Manual => {}
+        SortMode::Alphabetical => categories.sort_by_key(|category| category.to_lowercase()),
+        SortMode::MostUsed => categories.sort_by_key(|category| {
+            Reverse(
+                config.categories[*category]
+                    .values()
+                    .map(|entry| entry.uses)
+                    .sum::<u64>(),
+            )
+        }),
+        SortMode::RecentlyUsed => categories.sort_by_key(|category| {
+            Reverse(
+                config.categories[*category]
+                    .values()
+                    .filter_map(|entry| entry.last_used)
+                    .max(),
+            )
+        }),
+    }
+    categories
+}
+
+fn sorted_commands(
+    commands: &IndexMap<String, CommandEntry>,
+    sort: SortMode,
+) -> Vec<(&String, &CommandEntry)> {
+    let mut commands: Vec<(&String, &CommandEntry)> = commands.iter().collect();
+    match sort {
+        SortMode::Manual => {}
+        SortMode::Alphabetical => commands.sort_by_key(|(alias, _)| alias.to_lowercase()),
+        SortMode::MostUsed => commands.sort_by_key(|(_, entry)| Reverse(entry.uses)),
+        SortMode::RecentlyUsed => commands.sort_by_key(|(_, entry)| Reverse(entry.last_used)),
+    }
+    commands
+}
+
+// Counts a run of the command for the most-used and recently-used orders
+fn record_use(category: &str, alias: &str, config: &mut Config, path: &Path) {
+    if let Some(entry) = config
+        .categories
+        .get_mut(category)
+        .and_then(|commands| commands.get_mut(alias))
+    {
+        entry.uses += 1;
+        entry.last_used = Some(now());
+        update_config_file(config, path);
+    }
+}
+
+// A command found by `search`, with the matched character positions of each field
+struct SearchResult {
+    category: String,
+    alias: String,
+    score: i64,
+    category_matches: Vec<usize>,
+    alias_matches: Vec<usize>,
+    command_matches: Vec<usize>,
+    description_matches: Vec<usize>,
+}
+
+// Every word of the query has to match one of the fields, matches in aliases count most
+fn search_commands(query: &str, config: &Config) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+    for (category, commands) in &config.categories {
+        for (alias, entry) in commands {
+            let description = entry.description.as_deref().unwrap_or_default();
+            let fields = [
+                (alias.as_str(), 4),
+                (category.as_str(), 2),
+                (description, 2),
+                (entry.command.as_str(), 1),
+            ];
+            let mut matches: [Vec<usize>; 4] = Default::default();
+            let mut score = 0;
+            let mut matched_all = true;
+
+            for word in query.split_whitespace() {
+                let best = fields
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, (text, weight))| {
+                        fuzzy_match(word, text)
+                            .map(|(score, positions)| (score * weight, i, positions))
+                    })
+                    .max_by_key(|(score, _, _)| *score);
+                match best {
+                    Some((word_score, i, positions)) => {
+                        score += word_score;
+                        matches[i].extend(positions);
+                    }
+                    None => {
+                        matched_all = false;
+                        break;
+                    }
+                }
+            }
+
+            if matched_all {
+                let [alias_matches, category_matches, description_matches, command_matches] =
+                    matches;
+                results.push(SearchResult {
+                    category: category.clone(),
+                    alias: alias.clone(),
+                    score,
+                    category_matches,
+                    alias_matches,
+                    command_matches,
+                    description_matches,
+                });
+            }
+        }
+    }
+    results.sort_by_key(|result| Reverse(result.score));
+    results
+}
+
+// Scores how well the characters of the query appear in order in the text, ignoring case.
+// Substrings score best, then characters that follow each other or start a word
+fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    // Lowercased per character, so positions still match the original text
+    let lowercase = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let query: Vec<char> = query.chars().map(lowercase).collect();
+    let text: Vec<char> = text.chars().map(lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    if let Some(start) = text.windows(query.len()).position(|window| window == query) {
+        let at_word_start = start == 0 || !text[start - 1].is_alphanumeric();
+        let score = 10 * query.len() as i64 + if at_word_start { 10 } else { 0 };
+        return Some((score, (start..start + query.len()).collect()));
+    }
+
+    let mut positions = Vec::new();
+    let mut score = 0;
+    let mut next = 0;
+    for c in &query {
+        let position = next + text[next..].iter().position(|t| t == c)?;
+        score += if positions.last() == Some(&(position.wrapping_sub(1))) {
+            5
+        } else if position == 0 || !text[position - 1].is_alphanumeric() {
+            3
+        } else {
+            1
+        };
+        positions.push(position);
+        next = position + 1;
+    }
+    Some((score, positions))
+}
+
+fn highlight_spans(text: &str, matches: &[usize], style: Style) -> Vec<Span<'static>> {
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if matches.contains(&i) {
+                Span::styled(
+                    c.to_string(),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                )
+            } else {
+                Span::styled(c.to_string(), style)
+            }
+        })
+        .collect()
+}
+
+// Styles the text with matched characters highlighted, in runs so colors are not reset per character
+fn highlight(text: &str, matches: &[usize], style: fn(&str) -> ColoredString) -> String {
+    let mut highlighted = String::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = matches.contains(&i);
+        if matched != run_matched && !run.is_empty() {
+            highlighted.push_str(&highlight_run(&run, run_matched, style));
+            run.clear();
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    highlighted.push_str(&highlight_run(&run, run_matched, style));
+    highlighted
+}
+
+fn highlight_run(run: &str, matched: bool, style: fn(&str) -> ColoredString) -> String {
+    if matched {
+        run.yellow().bold().underline().to_string()
+    } else {
+        style(run).to_string()
+    }
+}
+
+fn print_search_results(results: &[SearchResult], config: &Config) {
+    for (i, result) in results.iter().enumerate() {
+        let entry = &config.categories[&result.category][&result.alias];
+        println!(
+            "{:>3}. {}/{} {}  {}",
+            i + 1,
+            highlight(&result.category, &result.category_matches, |s| s
+                .red()
+                .bold()),
+            highlight(&result.alias, &result.alias_matches, |s| s.green().bold()),
+            "➜".yellow().bold(),
+            highlight(&entry.command, &result.command_matches, |s| s.normal())
+        );
+        if let Some(description) = &entry.description {
+            println!(
+                "       {}",
+                highlight(description, &result.description_matches, |s| s.italic())
+            );
+        }
+    }
+}
+
 fn update_config_file(config: &Config, path: &Path) {
     let new_config_json = serde_json::to_string(config).expect("Failed to serialize config");
     fs::write(path, new_config_json).expect("Failed to write to config file");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotes_of(command: &str) -> Vec<Quotes> {
+        find_placeholders(command)
+            .unwrap()
+            .iter()
+            .flat_map(|placeholder| &placeholder.occurrences)
+            .map(|occurrence| occurrence.quotes)
+            .collect()
+    }
+
+    fn substitute(command: &str, value: &str) -> String {
+        let placeholders = find_placeholders(command).unwrap();
+        substitute_placeholders(command, &placeholders, &[value.to_string()])
+    }
+
+    #[test]
+    fn finds_quotes_around_placeholders() {
+        assert_eq!(quotes_of("echo <[msg]>"), [Quotes::None]);
+        assert_eq!(quotes_of("echo '<[msg]>'"), [Quotes::Single]);
+        assert_eq!(quotes_of(r#"echo "msg: <[msg]>""#), [Quotes::Double]);
+        assert_eq!(
+            quotes_of(r#"echo "<[a]>" '<[b]>' <[c]> "it's <[d]>""#),
+            [Quotes::Double, Quotes::Single, Quotes::None, Quotes::Double]
+        );
+        assert_eq!(
+            quotes_of(r#"echo \" <[a]> "\"<[b]>" 'a\'<[c]>"#),
+            [Quotes::None, Quotes::Double, Quotes::None]
+        );
+    }
+
+    #[test]
+    fn finds_repeated_placeholders_and_options() {
+        let placeholders = find_placeholders("echo <[a:x]> <[b|1,2]> <[a]> <[!c]>").unwrap();
+        assert_eq!(placeholders.len(), 3);
+        assert_eq!(placeholders[0].default.as_deref(), Some("x"));
+        assert_eq!(placeholders[0].occurrences.len(), 2);
+        assert_eq!(placeholders[1].choices, ["1", "2"]);
+        assert!(placeholders[2].occurrences[0].raw);
+        assert!(find_placeholders("echo <[a").is_err());
+    }
+
+    #[test]
+    fn quotes_values_for_their_context() {
+        assert_eq!(substitute("echo <[m]>", "fix bug"), "echo 'fix bug'");
+        assert_eq!(substitute("echo <[m]>", "it's"), r"echo 'it'\''s'");
+        assert_eq!(substitute("echo '<[m]>'", "it's $x"), r"echo 'it'\''s $x'");
+        assert_eq!(
+            substitute(r#"echo "msg: <[m]>""#, "fix bug"),
+            r#"echo "msg: fix bug""#
+        );
+        assert_eq!(
+            substitute(r#"echo "<[m]>""#, r#"$(a) `b` "c" \d"#),
+            r#"echo "\$(a) \`b\` \"c\" \\d""#
+        );
+        assert_eq!(substitute("echo <[!m]>", "$HOME"), "echo $HOME");
+    }
+
+    #[test]
+    fn shell_quotes_values() {
+        assert_eq!(shell_quote("plain-value_1.txt"), "plain-value_1.txt");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("$(rm -rf x)"), "'$(rm -rf x)'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    fn config_with(commands: &[(&str, &str, &str)]) -> Config {
+        let mut config: Config = serde_json::from_str(r#"{"categories":{}}"#).unwrap();
+        for (category, alias, command) in commands {
+            config
+                .categories
+                .entry(category.to_string())
+                .or_default()
+                .insert(alias.to_string(), CommandEntry::new(command));
+        }
+        config
+    }
+
+    fn aliases(config: &Config, category: &str) -> Vec<String> {
+        config.categories[category].keys().cloned().collect()
+    }
+
+    #[test]
+    fn reads_commands_stored_as_strings() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "categories": {
+                    "net": {
+                        "ping": "ping example.com",
+                        "curl": {
+                            "command": "curl <[url]>",
+                            "cwd": "/tmp",
+                            "description": "Fetch a page",
+                            "tags": ["web"],
+                            "uses": 3
+                        },
+                        "dig": "dig example.com"
+                    },
+                    "git": { "st": "git status" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.categories.keys().collect::<Vec<_>>(), ["net", "git"]);
+        assert_eq!(aliases(&config, "net"), ["ping", "curl", "dig"]);
+
+        let ping = &config.categories["net"]["ping"];
+        assert_eq!(ping.command, "ping example.com");
+        assert!(ping.cwd.is_none() && ping.description.is_none() && ping.tags.is_empty());
+        assert_eq!(ping.uses, 0);
+
+        let curl = &config.categories["net"]["curl"];
+        assert_eq!(curl.command, "curl <[url]>");
+        assert_eq!(curl.cwd.as_deref(), Some("/tmp"));
+        assert_eq!(curl.description.as_deref(), Some("Fetch a page"));
+        assert_eq!(curl.tags, ["web"]);
+        assert_eq!(curl.uses, 3);
+        assert_eq!(config.categories["git"]["st"].command, "git status");
+    }
+
+    #[test]
+    fn transfers_commands_on_conflict() {
+        let mut config = config_with(&[("g", "ping", "ping a"), ("h", "ping", "ping b")]);
+        assert!(transfer_command(
+            "g",
+            "ping",
+            "h",
+            "ping",
+            OnConflict::Fail,
+            false,
+            &mut config
+        )
+        .is_err());
+        assert_eq!(aliases(&config, "g"), ["ping"]);
+
+        let moved = transfer_command(
+            "g",
+            "ping",
+            "h",
+            "ping",
+            OnConflict::Overwrite,
+            false,
+            &mut config,
+        );
+        assert_eq!(moved.unwrap(), "ping");
+        assert!(aliases(&config, "g").is_empty());
+        assert_eq!(config.categories["h"]["ping"].command, "ping a");
+
+        let copied = transfer_command(
+            "h",
+            "ping",
+            "h",
+            "ping",
+            OnConflict::Suffix,
+            true,
+            &mut config,
+        );
+        assert_eq!(copied.unwrap(), "ping-2");
+        let copied = transfer_command(
+            "h",
+            "ping",
+            "h",
+            "ping",
+            OnConflict::Suffix,
+            true,
+            &mut config,
+        );
+        assert_eq!(copied.unwrap(), "ping-3");
+        assert_eq!(aliases(&config, "h"), ["ping", "ping-2", "ping-3"]);
+
+        for (on_conflict, copy) in [
+            (OnConflict::Fail, true),
+            (OnConflict::Overwrite, true),
+            (OnConflict::Suffix, false),
+        ] {
+            assert!(
+                transfer_command("h", "ping", "h", "ping", on_conflict, copy, &mut config).is_err()
+            );
+        }
+        assert_eq!(aliases(&config, "h"), ["ping", "ping-2", "ping-3"]);
+    }
+
+    #[test]
+    fn finds_positional_args_in_shell_text() {
+        assert!(uses_positional_args("echo $1"));
+        assert!(uses_positional_args(r#"echo "$@""#));
+        assert!(uses_positional_args("echo ${2:-x} <[a]>"));
+        assert!(uses_positional_args("awk '{print $2}' \"$1\""));
+        assert!(!uses_positional_args("echo 'a b c' | awk '{print $2}'"));
+        assert!(!uses_positional_args(r"echo \$1"));
+        assert!(!uses_positional_args("echo <[a:$1]>"));
+        assert!(!uses_positional_args("echo $HOME $0"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn commands_run_without_blocked_signals() {
+        let grep = interpreter(Some("grep -qE"));
+        let status = ["/proc/self/status".to_string()];
+        let exit_code = execute_command("^SigBlk:[[:space:]]*0+$", &status, None, &[], &grep);
+        assert_eq!(exit_code, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn substituted_values_stay_literal_in_sh() {
+        let value = r#"$(echo INJECTED) `echo x` "q" 'it's' \n"#;
+        for command in [
+            "printf %s <[m]>",
+            "printf %s '<[m]>'",
+            r#"printf %s "<[m]>""#,
+        ] {
+            let output = processCommand::new("sh")
+                .arg("-c")
+                .arg(substitute(command, value))
+                .output()
+                .unwrap();
+            let printed = String::from_utf8(output.stdout).unwrap();
+            assert_eq!(printed, value, "{}", command);
+        }
+    }
+}